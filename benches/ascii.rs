@@ -0,0 +1,68 @@
+//! Compares `gestalt_ratio` on ASCII input, which skips grapheme
+//! segmentation, against comparing the same strings grapheme by
+//! grapheme. Run with `cargo bench`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use gestalt_ratio::{gestalt_ratio, gestalt_ratio_with, Tokenizer};
+
+const IDENTIFIERS: &[&str] = &[
+    "gestalt_ratio",
+    "gestalt_ratio_seq",
+    "matching_blocks",
+    "longest_common_subseq_idxs",
+    "collect_matching_blocks",
+    "GestaltMatcher",
+    "quick_ratio",
+    "real_quick_ratio",
+    "close_matches",
+    "DifflibCompat",
+];
+
+const PARAGRAPHS: &[&str] = &[
+    "Ratcliff-Obershelp String Matching, otherwise known as Gestalt \
+     Pattern Matching. This function computes a similarity score \
+     between two strings, based on recursively looking at longest \
+     common substrings.",
+    "Ratcliff/Obershelp pattern recognition, otherwise known as gestalt \
+     pattern matching. This method computes the similarity of two \
+     strings, based on recursively finding the longest common \
+     substring and the matches to either side of it.",
+    "This metric is intended to show strings which look similar as more \
+     similar, and was originally described by John W. Ratcliff and \
+     John A. Obershelp in Dr. Dobbs Journal in 1988.",
+];
+
+/// Runs `f` on every pair of `inputs` `rounds` times, returning the
+/// time taken per comparison.
+fn time(inputs: &[&str], rounds: usize, f: impl Fn(&str, &str) -> f64) -> Duration {
+    let start = Instant::now();
+    for _ in 0..rounds {
+        for s1 in inputs {
+            for s2 in inputs {
+                black_box(f(black_box(s1), black_box(s2)));
+            }
+        }
+    }
+    start.elapsed() / (rounds * inputs.len() * inputs.len()) as u32
+}
+
+fn bench(name: &str, inputs: &[&str], rounds: usize) {
+    let bytes = time(inputs, rounds, gestalt_ratio);
+    let graphemes = time(inputs, rounds, |s1, s2| {
+        gestalt_ratio_with(s1, s2, Tokenizer::Graphemes)
+    });
+    println!("{}", name);
+    println!("  bytes:     {:?} per comparison", bytes);
+    println!("  graphemes: {:?} per comparison", graphemes);
+    println!(
+        "  speedup:   {:.2}x",
+        graphemes.as_secs_f64() / bytes.as_secs_f64()
+    );
+}
+
+fn main() {
+    bench("ascii identifiers", IDENTIFIERS, 2000);
+    bench("ascii paragraphs", PARAGRAPHS, 200);
+}
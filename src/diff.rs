@@ -0,0 +1,211 @@
+//! Unified and context diffs of lines, in the formats produced by
+//! `diff -u` and `diff -c` and accepted by `patch`.
+
+use crate::{DifflibCompat, Opcode, Tag};
+
+/// The file names and modification times shown in the header of a
+/// diff. Dates are free-form, and left out of the header when empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct DiffHeader<'a> {
+    /// The name of the original file.
+    pub from_file: &'a str,
+    /// The name of the new file.
+    pub to_file: &'a str,
+    /// The modification time of the original file, written after a tab.
+    pub from_date: &'a str,
+    /// The modification time of the new file, written after a tab.
+    pub to_date: &'a str,
+}
+
+/// Groups opcodes into hunks with up to `n` lines of context, like
+/// Python's `SequenceMatcher.get_grouped_opcodes`. Unchanged runs
+/// longer than `2 * n` split hunks, and are trimmed to `n` elements
+/// on each side of a change. If nothing changed, there are no groups.
+pub fn grouped_opcodes(opcodes: &[Opcode], n: usize) -> Vec<Vec<Opcode>> {
+    let mut codes = opcodes.to_vec();
+    if codes.is_empty() {
+        codes.push(Opcode {
+            tag: Tag::Equal,
+            a: 0..1,
+            b: 0..1,
+        });
+    }
+    if let Some(first) = codes.first_mut().filter(|op| op.tag == Tag::Equal) {
+        first.a.start = first.a.start.max(first.a.end.saturating_sub(n));
+        first.b.start = first.b.start.max(first.b.end.saturating_sub(n));
+    }
+    if let Some(last) = codes.last_mut().filter(|op| op.tag == Tag::Equal) {
+        last.a.end = last.a.end.min(last.a.start.saturating_add(n));
+        last.b.end = last.b.end.min(last.b.start.saturating_add(n));
+    }
+
+    let mut groups = Vec::new();
+    let mut group = Vec::new();
+    for mut op in codes {
+        // End the current group and start a new one whenever there
+        // is a long run with no changes.
+        if op.tag == Tag::Equal && op.a.len() > n.saturating_mul(2) {
+            group.push(Opcode {
+                tag: Tag::Equal,
+                a: op.a.start..op.a.end.min(op.a.start.saturating_add(n)),
+                b: op.b.start..op.b.end.min(op.b.start.saturating_add(n)),
+            });
+            groups.push(std::mem::take(&mut group));
+            op.a.start = op.a.start.max(op.a.end - n);
+            op.b.start = op.b.start.max(op.b.end - n);
+        }
+        group.push(op);
+    }
+    if !(group.is_empty() || group.len() == 1 && group[0].tag == Tag::Equal) {
+        groups.push(group);
+    }
+    groups
+}
+
+fn format_range_unified(start: usize, stop: usize) -> String {
+    let length = stop - start;
+    match length {
+        0 => format!("{},0", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, length),
+    }
+}
+
+fn format_range_context(start: usize, stop: usize) -> String {
+    let length = stop - start;
+    match length {
+        0 => format!("{}", start),
+        1 => format!("{}", start + 1),
+        _ => format!("{},{}", start + 1, start + length),
+    }
+}
+
+/// Formats one line of a diff body. Lines are expected to keep their
+/// terminators; one without, which can only be the last line of its
+/// file, is terminated and followed by the marker `patch` expects.
+fn diff_line(prefix: &str, line: &str) -> String {
+    if line.ends_with('\n') {
+        format!("{}{}", prefix, line)
+    } else {
+        format!("{}{}\n\\ No newline at end of file\n", prefix, line)
+    }
+}
+
+fn file_line(marker: &str, file: &str, date: &str) -> String {
+    if date.is_empty() {
+        format!("{} {}\n", marker, file)
+    } else {
+        format!("{} {}\t{}\n", marker, file, date)
+    }
+}
+
+/// The opcodes between two lists of lines, matched the way Python's
+/// difflib matches them, so that diffs come out the same.
+fn line_opcodes<'s, S: AsRef<str>>(
+    a: &'s [S],
+    b: &'s [S],
+) -> (Vec<&'s str>, Vec<&'s str>, Vec<Opcode>) {
+    let a: Vec<&str> = a.iter().map(AsRef::as_ref).collect();
+    let b: Vec<&str> = b.iter().map(AsRef::as_ref).collect();
+    let opcodes = DifflibCompat::new(&b).opcodes(&a);
+    (a, b, opcodes)
+}
+
+/// Compares two lists of lines and returns the differences as a
+/// unified diff with `n` lines of context, like Python's
+/// `difflib.unified_diff`. Lines should keep their line terminators,
+/// as from `str::split_inclusive('\n')`, and every line of the output
+/// is terminated, so the result can be concatenated and fed to
+/// `patch`. Unlike in Python, a last line without a terminator is
+/// followed by `\ No newline at end of file`. If the lines are the
+/// same, the diff is empty.
+pub fn unified_diff<S: AsRef<str>>(a: &[S], b: &[S], header: &DiffHeader, n: usize) -> Vec<String> {
+    let (a, b, opcodes) = line_opcodes(a, b);
+    let mut out = Vec::new();
+    for group in grouped_opcodes(&opcodes, n) {
+        if out.is_empty() {
+            out.push(file_line("---", header.from_file, header.from_date));
+            out.push(file_line("+++", header.to_file, header.to_date));
+        }
+        let (first, last) = (&group[0], &group[group.len() - 1]);
+        out.push(format!(
+            "@@ -{} +{} @@\n",
+            format_range_unified(first.a.start, last.a.end),
+            format_range_unified(first.b.start, last.b.end)
+        ));
+        for op in &group {
+            if op.tag == Tag::Equal {
+                out.extend(a[op.a.clone()].iter().map(|line| diff_line(" ", line)));
+                continue;
+            }
+            if op.tag == Tag::Replace || op.tag == Tag::Delete {
+                out.extend(a[op.a.clone()].iter().map(|line| diff_line("-", line)));
+            }
+            if op.tag == Tag::Replace || op.tag == Tag::Insert {
+                out.extend(b[op.b.clone()].iter().map(|line| diff_line("+", line)));
+            }
+        }
+    }
+    out
+}
+
+/// Compares two lists of lines and returns the differences as a
+/// context diff with `n` lines of context, like Python's
+/// `difflib.context_diff`. See [`unified_diff`] for the format of the
+/// input and output.
+pub fn context_diff<S: AsRef<str>>(a: &[S], b: &[S], header: &DiffHeader, n: usize) -> Vec<String> {
+    fn prefix(tag: Tag) -> &'static str {
+        match tag {
+            Tag::Insert => "+ ",
+            Tag::Delete => "- ",
+            Tag::Replace => "! ",
+            Tag::Equal => "  ",
+        }
+    }
+
+    let (a, b, opcodes) = line_opcodes(a, b);
+    let mut out = Vec::new();
+    for group in grouped_opcodes(&opcodes, n) {
+        if out.is_empty() {
+            out.push(file_line("***", header.from_file, header.from_date));
+            out.push(file_line("---", header.to_file, header.to_date));
+        }
+        let (first, last) = (&group[0], &group[group.len() - 1]);
+        out.push("***************\n".to_string());
+
+        out.push(format!(
+            "*** {} ****\n",
+            format_range_context(first.a.start, last.a.end)
+        ));
+        if group
+            .iter()
+            .any(|op| op.tag == Tag::Replace || op.tag == Tag::Delete)
+        {
+            for op in group.iter().filter(|op| op.tag != Tag::Insert) {
+                out.extend(
+                    a[op.a.clone()]
+                        .iter()
+                        .map(|line| diff_line(prefix(op.tag), line)),
+                );
+            }
+        }
+
+        out.push(format!(
+            "--- {} ----\n",
+            format_range_context(first.b.start, last.b.end)
+        ));
+        if group
+            .iter()
+            .any(|op| op.tag == Tag::Replace || op.tag == Tag::Insert)
+        {
+            for op in group.iter().filter(|op| op.tag != Tag::Delete) {
+                out.extend(
+                    b[op.b.clone()]
+                        .iter()
+                        .map(|line| diff_line(prefix(op.tag), line)),
+                );
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,93 @@
+//! Reproducing the scores of Python's `difflib.SequenceMatcher`
+//! exactly.
+//!
+//! The gestalt ratio computed by this crate is the same algorithm
+//! that `SequenceMatcher.ratio()` uses, and longest matches are
+//! chosen by the same rule: earliest in the first sequence, then
+//! earliest in the second. Python's scores can still differ from
+//! [`gestalt_ratio`](crate::gestalt_ratio) for two reasons:
+//!
+//! - Python compares strings by code point, while `gestalt_ratio`
+//!   compares extended graphemes.
+//! - `SequenceMatcher` uses the autojunk heuristic by default, which
+//!   stops elements making up more than 1% of a second sequence of
+//!   200 or more elements from anchoring a match.
+//!
+//! [`DifflibCompat`] and [`difflib_ratio`] make the same choices as
+//! Python, so their results are identical to it, bit for bit.
+
+use std::hash::Hash;
+
+use crate::index::Index;
+use crate::{
+    calculate_ratio, finish_blocks, matching_items, opcodes_from_blocks, raw_blocks_indexed, Match,
+    Opcode,
+};
+
+/// A port of Python's `SequenceMatcher`, with a fixed second
+/// sequence `b` that first sequences are compared against. As in
+/// Python, autojunk is on unless turned off with
+/// [`autojunk`](DifflibCompat::autojunk).
+pub struct DifflibCompat<'a, T> {
+    index: Index<'a, T>,
+}
+
+impl<'a, T: Hash + Eq> DifflibCompat<'a, T> {
+    /// The equivalent of `SequenceMatcher(None, a, b)`.
+    pub fn new(b: &'a [T]) -> Self {
+        DifflibCompat::with_junk(b, |_| false)
+    }
+
+    /// The equivalent of `SequenceMatcher(isjunk, a, b)`.
+    pub fn with_junk<F: Fn(&T) -> bool>(b: &'a [T], isjunk: F) -> Self {
+        let mut index = Index::with_junk(b.iter(), isjunk);
+        index.set_autojunk(true);
+        DifflibCompat { index }
+    }
+
+    /// Sets the `autojunk` argument of `SequenceMatcher`.
+    pub fn autojunk(mut self, autojunk: bool) -> Self {
+        self.index.set_autojunk(autojunk);
+        self
+    }
+
+    /// `SequenceMatcher.ratio()`, with `a` as the first sequence.
+    pub fn ratio(&self, a: &[T]) -> f64 {
+        let raw = raw_blocks_indexed(a, &self.index);
+        calculate_ratio(matching_items(&raw), a.len() + self.index.len())
+    }
+
+    /// `SequenceMatcher.quick_ratio()`, with `a` as the first
+    /// sequence.
+    pub fn quick_ratio(&self, a: &[T]) -> f64 {
+        calculate_ratio(self.index.common_items(a), a.len() + self.index.len())
+    }
+
+    /// `SequenceMatcher.real_quick_ratio()`, with `a` as the first
+    /// sequence.
+    pub fn real_quick_ratio(&self, a: &[T]) -> f64 {
+        let lb = self.index.len();
+        calculate_ratio(a.len().min(lb), a.len() + lb)
+    }
+
+    /// `SequenceMatcher.get_matching_blocks()`, with `a` as the first
+    /// sequence.
+    pub fn matching_blocks(&self, a: &[T]) -> Vec<Match> {
+        let raw = raw_blocks_indexed(a, &self.index);
+        finish_blocks(raw, a.len(), self.index.len())
+    }
+
+    /// `SequenceMatcher.get_opcodes()`, with `a` as the first
+    /// sequence.
+    pub fn opcodes(&self, a: &[T]) -> Vec<Opcode> {
+        opcodes_from_blocks(&self.matching_blocks(a))
+    }
+}
+
+/// `SequenceMatcher(None, s1, s2).ratio()`: the gestalt ratio of two
+/// strings compared code point by code point, with autojunk.
+pub fn difflib_ratio(s1: &str, s2: &str) -> f64 {
+    let a: Vec<char> = s1.chars().collect();
+    let b: Vec<char> = s2.chars().collect();
+    DifflibCompat::new(&b).ratio(&a)
+}
@@ -0,0 +1,291 @@
+//! Side-by-side HTML tables of the differences between two lists of
+//! lines, like Python's `difflib.HtmlDiff`. Only built with the
+//! `html` feature.
+
+use std::borrow::Cow;
+
+use crate::ndiff::{delta_lines, DeltaLine};
+use crate::{Opcode, Tag};
+
+/// The styles for the classes used in tables, the same as the ones
+/// Python's `HtmlDiff` puts in its files.
+const STYLES: &str = "\
+        table.diff {font-family:Courier; border:medium;}
+        .diff_header {background-color:#e0e0e0}
+        td.diff_header {text-align:right}
+        .diff_add {background-color:#aaffaa}
+        .diff_chg {background-color:#ffff77}
+        .diff_sub {background-color:#ffaaaa}
+";
+
+/// Renders the differences between two lists of lines as an HTML
+/// table, with the lines of the first list on the left, the lines of
+/// the second on the right, and line numbers on both sides.
+///
+/// Lines are paired up as by [`ndiff`](crate::ndiff). Similar changed
+/// lines are shown side by side, with the characters that changed
+/// highlighted; other changed lines are highlighted as a whole. The
+/// markup uses the same classes as Python's `HtmlDiff`: `diff_add`,
+/// `diff_chg` and `diff_sub` for highlighted text, and `diff_header`
+/// for headers and line numbers. Python's "next change" links are not
+/// generated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HtmlDiff {
+    /// The number of columns between tab stops. Tabs are expanded to
+    /// spaces before the lines are compared. The default is 8.
+    pub tab_size: usize,
+    /// If set, only show changed lines and this many unchanged lines
+    /// around them. Each group of lines shown is a separate `tbody`.
+    /// The default is to show every line.
+    pub context: Option<usize>,
+}
+
+impl Default for HtmlDiff {
+    fn default() -> Self {
+        HtmlDiff {
+            tab_size: 8,
+            context: None,
+        }
+    }
+}
+
+/// One side of a row of the table.
+struct Cell {
+    number: usize,
+    html: String,
+}
+
+struct Row {
+    from: Option<Cell>,
+    to: Option<Cell>,
+    changed: bool,
+}
+
+/// Drops the line terminator from `line`, and expands its tabs.
+fn prepare(line: &str, tab_size: usize) -> Cow<'_, str> {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    if !line.contains('\t') {
+        return Cow::Borrowed(line);
+    }
+    let mut expanded = String::new();
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            if tab_size > 0 {
+                let spaces = tab_size - column % tab_size;
+                expanded.push_str(&" ".repeat(spaces));
+                column += spaces;
+            }
+        } else {
+            expanded.push(c);
+            column += 1;
+        }
+    }
+    Cow::Owned(expanded)
+}
+
+/// Escapes `text` for HTML, with non-breaking spaces so that runs of
+/// spaces keep their width.
+fn push_escaped(html: &mut String, text: impl IntoIterator<Item = char>) {
+    for c in text {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            ' ' => html.push_str("&nbsp;"),
+            _ => html.push(c),
+        }
+    }
+}
+
+fn push_span(html: &mut String, class: &str, text: impl IntoIterator<Item = char>) {
+    html.push_str("<span class=\"");
+    html.push_str(class);
+    html.push_str("\">");
+    push_escaped(html, text);
+    html.push_str("</span>");
+}
+
+/// A line that was only on one side, highlighted as a whole.
+fn whole_line(number: usize, line: &str, class: &str) -> Cell {
+    let mut html = String::new();
+    push_span(&mut html, class, line.chars());
+    Cell { number, html }
+}
+
+fn plain_line(number: usize, line: &str) -> Cell {
+    let mut html = String::new();
+    push_escaped(&mut html, line.chars());
+    Cell { number, html }
+}
+
+/// One side of a pair of similar lines, with the characters that
+/// changed highlighted.
+fn marked_line(number: usize, line: &str, opcodes: &[Opcode], first: bool) -> Cell {
+    let chars: Vec<char> = line.chars().collect();
+    let mut html = String::new();
+    for op in opcodes {
+        let range = if first { op.a.clone() } else { op.b.clone() };
+        let text = chars[range].iter().copied();
+        match (op.tag, first) {
+            (Tag::Equal, _) => push_escaped(&mut html, text),
+            (Tag::Replace, _) => push_span(&mut html, "diff_chg", text),
+            (Tag::Delete, true) => push_span(&mut html, "diff_sub", text),
+            (Tag::Insert, false) => push_span(&mut html, "diff_add", text),
+            _ => {}
+        }
+    }
+    Cell { number, html }
+}
+
+fn push_cell(html: &mut String, cell: &Option<Cell>) {
+    match cell {
+        Some(cell) => html.push_str(&format!(
+            "<td class=\"diff_header\">{}</td><td nowrap=\"nowrap\">{}</td>",
+            cell.number, cell.html
+        )),
+        None => html.push_str("<td class=\"diff_header\"></td><td nowrap=\"nowrap\"></td>"),
+    }
+}
+
+/// Outputs the pending deleted and inserted lines, side by side.
+fn flush(rows: &mut Vec<Row>, deleted: &mut Vec<Cell>, inserted: &mut Vec<Cell>) {
+    let (mut deleted, mut inserted) = (deleted.drain(..), inserted.drain(..));
+    loop {
+        let (from, to) = (deleted.next(), inserted.next());
+        if from.is_none() && to.is_none() {
+            break;
+        }
+        rows.push(Row {
+            from,
+            to,
+            changed: true,
+        });
+    }
+}
+
+/// Lays out the rows of the table, with deleted and inserted lines
+/// that weren't paired up side by side where they can be.
+fn rows(a: &[&str], b: &[&str]) -> Vec<Row> {
+    let mut rows = Vec::new();
+    let mut deleted = Vec::new();
+    let mut inserted = Vec::new();
+    // Common lines only say where they are in `a`; the next line
+    // of `b` is the one after the last one output.
+    let mut next_b = 0;
+    for line in delta_lines(a, b) {
+        match line {
+            DeltaLine::Deleted(i) => deleted.push(whole_line(i + 1, a[i], "diff_sub")),
+            DeltaLine::Inserted(j) => {
+                inserted.push(whole_line(j + 1, b[j], "diff_add"));
+                next_b = j + 1;
+            }
+            DeltaLine::Common(i) => {
+                flush(&mut rows, &mut deleted, &mut inserted);
+                rows.push(Row {
+                    from: Some(plain_line(i + 1, a[i])),
+                    to: Some(plain_line(next_b + 1, b[next_b])),
+                    changed: false,
+                });
+                next_b += 1;
+            }
+            DeltaLine::Changed(i, j, opcodes) => {
+                flush(&mut rows, &mut deleted, &mut inserted);
+                rows.push(Row {
+                    from: Some(marked_line(i + 1, a[i], &opcodes, true)),
+                    to: Some(marked_line(j + 1, b[j], &opcodes, false)),
+                    changed: true,
+                });
+                next_b = j + 1;
+            }
+        }
+    }
+    flush(&mut rows, &mut deleted, &mut inserted);
+    rows
+}
+
+impl HtmlDiff {
+    /// Returns an HTML table showing `a` and `b` side by side, with
+    /// `from_desc` and `to_desc` as the column headers. The headers
+    /// are left out if both are empty. Lines may keep their line
+    /// terminators, which aren't shown.
+    pub fn make_table<S: AsRef<str>>(
+        &self,
+        a: &[S],
+        b: &[S],
+        from_desc: &str,
+        to_desc: &str,
+    ) -> String {
+        let a: Vec<Cow<str>> = a
+            .iter()
+            .map(|line| prepare(line.as_ref(), self.tab_size))
+            .collect();
+        let b: Vec<Cow<str>> = b
+            .iter()
+            .map(|line| prepare(line.as_ref(), self.tab_size))
+            .collect();
+        let a: Vec<&str> = a.iter().map(AsRef::as_ref).collect();
+        let b: Vec<&str> = b.iter().map(AsRef::as_ref).collect();
+        let rows = rows(&a, &b);
+
+        // Which rows to show, as runs of consecutive rows.
+        let mut hunks: Vec<std::ops::Range<usize>> = Vec::new();
+        match self.context {
+            None => hunks.push(0..rows.len()),
+            Some(n) => {
+                for (k, _) in rows.iter().enumerate().filter(|(_, row)| row.changed) {
+                    let shown =
+                        k.saturating_sub(n)..k.saturating_add(n).saturating_add(1).min(rows.len());
+                    match hunks.last_mut() {
+                        Some(last) if last.end >= shown.start => last.end = shown.end,
+                        _ => hunks.push(shown),
+                    }
+                }
+            }
+        }
+
+        let mut html = String::from("<table class=\"diff\">\n");
+        if !from_desc.is_empty() || !to_desc.is_empty() {
+            html.push_str("<thead><tr><th colspan=\"2\" class=\"diff_header\">");
+            push_escaped(&mut html, from_desc.chars());
+            html.push_str("</th><th colspan=\"2\" class=\"diff_header\">");
+            push_escaped(&mut html, to_desc.chars());
+            html.push_str("</th></tr></thead>\n");
+        }
+        if hunks.is_empty() {
+            html.push_str(
+                "<tbody><tr><td colspan=\"4\">&nbsp;No Differences Found&nbsp;</td></tr></tbody>\n",
+            );
+        }
+        for hunk in hunks {
+            html.push_str("<tbody>\n");
+            for row in &rows[hunk] {
+                html.push_str("<tr>");
+                push_cell(&mut html, &row.from);
+                push_cell(&mut html, &row.to);
+                html.push_str("</tr>\n");
+            }
+            html.push_str("</tbody>\n");
+        }
+        html.push_str("</table>\n");
+        html
+    }
+
+    /// Returns a complete HTML document containing the table from
+    /// [`make_table`](HtmlDiff::make_table), with styles for its
+    /// classes.
+    pub fn make_file<S: AsRef<str>>(
+        &self,
+        a: &[S],
+        b: &[S],
+        from_desc: &str,
+        to_desc: &str,
+    ) -> String {
+        format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title></title>\n<style type=\"text/css\">\n{}</style>\n</head>\n<body>\n{}</body>\n</html>\n",
+            STYLES,
+            self.make_table(a, b, from_desc, to_desc)
+        )
+    }
+}
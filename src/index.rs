@@ -0,0 +1,186 @@
+//! Hash-indexed longest match search, following the `b2j` index of
+//! Python's `SequenceMatcher`.
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::Match;
+
+/// Everything the index knows about one distinct element of `b`.
+struct Entry {
+    /// The positions the element occurs at, in increasing order.
+    positions: Vec<usize>,
+    /// Whether the caller marked the element as junk.
+    junk: bool,
+    /// Whether the element was found to be too common to anchor a
+    /// match, by [`Index::set_autojunk`].
+    popular: bool,
+}
+
+/// Maps each element of the second sequence to the positions it
+/// occurs at, in increasing order.
+pub(crate) struct Index<'a, T: ?Sized> {
+    b: Vec<&'a T>,
+    b2j: HashMap<&'a T, Entry>,
+}
+
+impl<'a, T: ?Sized + Hash + Eq> Index<'a, T> {
+    pub(crate) fn new<I: IntoIterator<Item = &'a T>>(b: I) -> Self {
+        Index::with_junk(b, |_| false)
+    }
+
+    /// Builds an index in which the elements of `b` that `isjunk`
+    /// accepts can't start a match. See [`longest_match`] for how
+    /// they can still be part of one.
+    ///
+    /// [`longest_match`]: Index::longest_match
+    pub(crate) fn with_junk<I, F>(b: I, isjunk: F) -> Self
+    where
+        I: IntoIterator<Item = &'a T>,
+        F: Fn(&T) -> bool,
+    {
+        let b: Vec<&'a T> = b.into_iter().collect();
+        let mut b2j: HashMap<&'a T, Entry> = HashMap::new();
+        for (j, &elt) in b.iter().enumerate() {
+            b2j.entry(elt)
+                .or_insert_with(|| Entry {
+                    positions: Vec::new(),
+                    junk: isjunk(elt),
+                    popular: false,
+                })
+                .positions
+                .push(j);
+        }
+        Index { b, b2j }
+    }
+
+    /// Python's autojunk heuristic: in sequences of at least 200
+    /// elements, any non-junk element making up more than 1% of the
+    /// sequence is considered popular, and can't anchor a match.
+    /// Unlike junk, popular elements still extend a match when they
+    /// are on either side of it. Passing `false` turns the heuristic
+    /// back off.
+    pub(crate) fn set_autojunk(&mut self, autojunk: bool) {
+        let n = self.b.len();
+        let ntest = n / 100 + 1;
+        for entry in self.b2j.values_mut() {
+            entry.popular = autojunk && n >= 200 && !entry.junk && entry.positions.len() > ntest;
+        }
+    }
+
+    /// The length of the sequence this index was built from.
+    pub(crate) fn len(&self) -> usize {
+        self.b.len()
+    }
+
+    /// Whether the element at `b[j]` is junk.
+    fn is_junk(&self, j: usize) -> bool {
+        self.b2j[self.b[j]].junk
+    }
+
+    /// Counts the elements of `a` that also occur in `b`, as a
+    /// multiset: an element occurring twice in `a` and once in `b`
+    /// counts once. Junk is counted like everything else.
+    pub(crate) fn common_items<A: Borrow<T>>(&self, a: &[A]) -> usize {
+        let mut used: HashMap<&T, usize> = HashMap::new();
+        let mut matches = 0;
+        for elt in a {
+            let elt = elt.borrow();
+            let available = self.b2j.get(elt).map_or(0, |entry| entry.positions.len());
+            let used = used.entry(elt).or_insert(0);
+            if *used < available {
+                *used += 1;
+                matches += 1;
+            }
+        }
+        matches
+    }
+
+    /// Finds the longest common run of `a[alo..ahi]` and
+    /// `b[blo..bhi]`, where `b` is the sequence this index was built
+    /// from. Ties are broken exactly as in the dynamic programming
+    /// search: the run ending earliest in `a` wins, and then the one
+    /// ending earliest in `b`.
+    ///
+    /// Only the positions where `a[i]` actually occurs in `b` are
+    /// visited, so this is much faster than the full table when most
+    /// pairs of elements differ.
+    ///
+    /// If there is junk, the run is first found among non-junk
+    /// elements only, and then extended with any equal junk on either
+    /// side, the way Python's `find_longest_match` does. So junk never
+    /// anchors a match, but a run of junk next to a real match is
+    /// counted along with it. Popular elements are skipped the same
+    /// way, but extend the match before junk does.
+    pub(crate) fn longest_match<A: Borrow<T>>(
+        &self,
+        a: &[A],
+        alo: usize,
+        ahi: usize,
+        blo: usize,
+        bhi: usize,
+    ) -> Match {
+        let mut best = Match {
+            a: alo,
+            b: blo,
+            size: 0,
+        };
+        // `prev[j - blo + 1]` is the length of the run ending at
+        // a[i - 1] and b[j]. Only the entries listed in `prev_set` are
+        // nonzero, so clearing a row doesn't cost the whole width.
+        let mut prev = vec![0; bhi.saturating_sub(blo) + 1];
+        let mut cur = prev.clone();
+        let mut prev_set = Vec::new();
+        let mut cur_set = Vec::new();
+
+        for (i, elt) in a.iter().enumerate().take(ahi).skip(alo) {
+            let positions = match self.b2j.get(elt.borrow()) {
+                Some(entry) if !entry.junk && !entry.popular => &entry.positions[..],
+                _ => &[],
+            };
+            let first = positions.partition_point(|&j| j < blo);
+            for &j in &positions[first..] {
+                if j >= bhi {
+                    break;
+                }
+                let k = prev[j - blo] + 1;
+                cur[j - blo + 1] = k;
+                cur_set.push(j - blo + 1);
+                if k > best.size {
+                    best = Match {
+                        a: i + 1 - k,
+                        b: j + 1 - k,
+                        size: k,
+                    };
+                }
+            }
+            for &x in &prev_set {
+                prev[x] = 0;
+            }
+            prev_set.clear();
+            std::mem::swap(&mut prev, &mut cur);
+            std::mem::swap(&mut prev_set, &mut cur_set);
+        }
+
+        for &junk in &[false, true] {
+            while best.a > alo
+                && best.b > blo
+                && self.is_junk(best.b - 1) == junk
+                && a[best.a - 1].borrow() == self.b[best.b - 1]
+            {
+                best.a -= 1;
+                best.b -= 1;
+                best.size += 1;
+            }
+            while best.a + best.size < ahi
+                && best.b + best.size < bhi
+                && self.is_junk(best.b + best.size) == junk
+                && a[best.a + best.size].borrow() == self.b[best.b + best.size]
+            {
+                best.size += 1;
+            }
+        }
+        best
+    }
+}
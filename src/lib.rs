@@ -2,20 +2,74 @@
 //! ==================================
 //!
 //! Ratcliff-Obershelp String Matching, otherwise known as Gestalt
-//! Pattern Matching. This crate contains a single function, which
+//! Pattern Matching. The main function of this crate, `gestalt_ratio`,
 //! computes a similarity score between two strings, based on
 //! recursively looking at longest common substrings. The algorithm is
 //! described in this wikipedia page:
 //! <https://en.wikipedia.org/wiki/Gestalt_Pattern_Matching>
 //!
+//! The substrings found along the way are available from
+//! `matching_blocks`, for callers that want to know what matched and
+//! not just how much.
+//!
+//! Python's `difflib.SequenceMatcher` implements the same algorithm,
+//! with a few different defaults. `DifflibCompat` and `difflib_ratio`
+//! follow those defaults, for callers that need the exact same scores
+//! as Python.
+//!
 //! Unicode Support
 //! ---------------
 //!
 //! As of version 0.2.0 this crate supports unicode strings. Strings
 //! are compared using their extended graphemes, as provided by the
 //! unicode_segmentation crate.
+//!
+//! Strings that render the same can still be made of different
+//! graphemes, for example when one uses a precomposed "é" and the
+//! other an "e" followed by a combining accent. `gestalt_ratio_with_options`
+//! can normalize, case fold and strip diacritics from strings before
+//! comparing them, and split them into something other than
+//! graphemes.
+//!
+//! Features
+//! --------
+//!
+//! The `html` feature adds `HtmlDiff`, which renders the differences
+//! between two texts as a side-by-side HTML table.
 
+extern crate caseless;
+extern crate unicode_normalization;
 extern crate unicode_segmentation;
+extern crate unicode_width;
+
+mod diff;
+mod difflib;
+#[cfg(feature = "html")]
+mod html;
+mod index;
+mod matcher;
+mod ndiff;
+mod options;
+mod term;
+mod tokenizer;
+mod weighted;
+
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::hash::Hash;
+use std::ops::Range;
+
+pub use diff::{context_diff, grouped_opcodes, unified_diff, DiffHeader};
+pub use difflib::{difflib_ratio, DifflibCompat};
+#[cfg(feature = "html")]
+pub use html::HtmlDiff;
+use index::Index;
+pub use matcher::GestaltMatcher;
+pub use ndiff::{ndiff, restore, Side};
+pub use options::{Normalization, Options};
+pub use term::{Highlight, TermDiff};
+pub use tokenizer::Tokenizer;
+pub use weighted::gestalt_ratio_weighted;
 
 use unicode_segmentation::UnicodeSegmentation;
 
@@ -56,50 +110,1286 @@ mod tests {
         // python3.8 difflib SequenceMatcher.
         assert_eq!(score, 0.7142857142857143);
     }
-}
 
-/// Produces a string similarity score between 0 and 1.
-fn longest_common_subseq_idxs<T: Eq>(s1: &[T], s2: &[T]) -> ((usize, usize), (usize, usize)) {
-    let mut max_length = 0;
-    let mut ending_index_1 = s1.len();
-    let mut ending_index_2 = s2.len();
-    let mut lookup = vec![vec![0; s2.len() + 1]; s1.len() + 1];
-
-    for (i, c1) in s1.iter().enumerate() {
-        for (j, c2) in s2.iter().enumerate() {
-            if c1 == c2 {
-                lookup[i + 1][j + 1] = lookup[i][j] + 1;
-                if lookup[i + 1][j + 1] > max_length {
-                    max_length = lookup[i + 1][j + 1];
-                    ending_index_1 = i + 1;
-                    ending_index_2 = j + 1;
-                }
+    #[test]
+    /// Expected blocks are from python3 difflib's get_matching_blocks.
+    fn matching_blocks_example() {
+        let blocks = matching_blocks("Ebojfm Mzpm", "Ebfo ef Mfpo");
+        let expected = [
+            (0, 0, 2),
+            (2, 3, 1),
+            (4, 6, 1),
+            (6, 7, 2),
+            (9, 10, 1),
+            (11, 12, 0),
+        ];
+        assert_eq!(blocks.len(), expected.len());
+        for (m, &(a, b, size)) in blocks.iter().zip(expected.iter()) {
+            assert_eq!(*m, Match { a, b, size });
+        }
+    }
+
+    #[test]
+    fn matching_blocks_sentinel() {
+        assert_eq!(
+            matching_blocks("", "abc"),
+            vec![Match {
+                a: 0,
+                b: 3,
+                size: 0
+            }]
+        );
+        let blocks = matching_blocks("x² + y²", "y² + z²");
+        assert_eq!(
+            blocks.last(),
+            Some(&Match {
+                a: 7,
+                b: 7,
+                size: 0
+            })
+        );
+        let total: usize = blocks.iter().map(|m| m.size).sum();
+        assert_eq!(total, 5);
+    }
+
+    #[test]
+    /// Expected opcodes are from the python3 difflib documentation
+    /// of get_opcodes.
+    fn opcodes_example() {
+        let ops = opcodes("qabxcd", "abycdf");
+        let expected = [
+            (Tag::Delete, 0..1, 0..0),
+            (Tag::Equal, 1..3, 0..2),
+            (Tag::Replace, 3..4, 2..3),
+            (Tag::Equal, 4..6, 3..5),
+            (Tag::Insert, 6..6, 5..6),
+        ];
+        assert_eq!(ops.len(), expected.len());
+        for (op, (tag, a, b)) in ops.iter().zip(expected.iter().cloned()) {
+            assert_eq!(*op, Opcode { tag, a, b });
+        }
+    }
+
+    #[test]
+    fn opcodes_seq_cover_inputs() {
+        let s1 = [1, 2, 3, 4, 5];
+        let s2 = [0, 2, 3, 5, 6];
+        let ops = opcodes_seq(&s1, &s2);
+        assert_eq!(ops.first().map(|op| (op.a.start, op.b.start)), Some((0, 0)));
+        assert_eq!(ops.last().map(|op| (op.a.end, op.b.end)), Some((5, 5)));
+        for pair in ops.windows(2) {
+            assert_eq!(pair[0].a.end, pair[1].a.start);
+            assert_eq!(pair[0].b.end, pair[1].b.start);
+        }
+        assert!(opcodes("", "").is_empty());
+    }
+
+    #[test]
+    /// The search table runs over the shorter input, so make sure
+    /// ties still break the same way, and the same way python3
+    /// difflib does, whichever side is shorter.
+    fn tie_breaking_independent_of_lengths() {
+        let blocks = matching_blocks("ba", "abcab");
+        assert_eq!(
+            blocks[0],
+            Match {
+                a: 0,
+                b: 1,
+                size: 1
+            }
+        );
+        assert_eq!(
+            blocks[1],
+            Match {
+                a: 1,
+                b: 3,
+                size: 1
+            }
+        );
+        let blocks = matching_blocks("abcab", "ba");
+        assert_eq!(
+            blocks[0],
+            Match {
+                a: 0,
+                b: 1,
+                size: 1
+            }
+        );
+        let blocks = matching_blocks("xy", "yxyx");
+        assert_eq!(
+            blocks[0],
+            Match {
+                a: 0,
+                b: 1,
+                size: 2
             }
+        );
+    }
+
+    /// A small deterministic generator, so the randomized tests below
+    /// don't need an extra dependency.
+    fn pseudo_random_seqs(seed: u64, count: usize, alphabet: u64, max_len: usize) -> Vec<Vec<u8>> {
+        let mut state = seed;
+        let mut next = move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            state >> 33
+        };
+        (0..count)
+            .map(|_| {
+                let len = next() as usize % (max_len + 1);
+                (0..len).map(|_| (next() % alphabet) as u8).collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn hashed_matches_dynamic_programming() {
+        for alphabet in [2, 4, 26] {
+            let seqs = pseudo_random_seqs(alphabet, 40, alphabet, 30);
+            for pair in seqs.windows(2) {
+                let (s1, s2) = (&pair[0], &pair[1]);
+                assert_eq!(
+                    gestalt_ratio_seq_hashed(s1, s2).to_bits(),
+                    gestalt_ratio_seq(s1, s2).to_bits(),
+                    "{:?} {:?}",
+                    s1,
+                    s2
+                );
+                assert_eq!(
+                    finish_blocks(raw_blocks_hashed::<_, _, u8>(s1, s2), s1.len(), s2.len()),
+                    matching_blocks_seq(s1, s2)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn hashed_lines() {
+        let s1: Vec<&str> = "fn main() {\n    let x = 1;\n    println!(x);\n}"
+            .lines()
+            .collect();
+        let s2: Vec<&str> = "fn main() {\n    let x = 2;\n    println!(x);\n}\n"
+            .lines()
+            .collect();
+        assert_eq!(gestalt_ratio_seq_hashed(&s1, &s2), 0.75);
+        assert_eq!(gestalt_ratio_seq(&s1, &s2), 0.75);
+    }
+
+    #[test]
+    fn matcher_agrees_with_free_functions() {
+        let matcher = GestaltMatcher::new("Wikimania");
+        for other in ["Wikimedia", "Wikipedia", "x² + y²", "", "mania"] {
+            assert_eq!(matcher.ratio(other), gestalt_ratio(other, "Wikimania"));
+            assert_eq!(
+                matcher.matching_blocks(other),
+                matching_blocks(other, "Wikimania")
+            );
+        }
+    }
+
+    #[test]
+    /// Expected values are from python3 difflib's quick_ratio and
+    /// real_quick_ratio.
+    fn quick_ratios() {
+        assert_eq!(quick_ratio("abcd", "bcde"), 0.75);
+        assert_eq!(real_quick_ratio("abcd", "bcde"), 1.0);
+        assert_eq!(
+            quick_ratio("Ebojfm Mzpm", "Ebfo ef Mfpo"),
+            0.6086956521739131
+        );
+        assert_eq!(real_quick_ratio("x² + y²", "z²"), 0.4444444444444444);
+        assert_eq!(quick_ratio_seq(&[1, 1, 2], &[1, 2, 2]), 2.0 / 3.0);
+        assert_eq!(real_quick_ratio_seq(&[1, 1, 2], &[3]), 0.5);
+    }
+
+    #[test]
+    fn quick_ratios_are_upper_bounds() {
+        let seqs = pseudo_random_seqs(7, 60, 5, 20);
+        for pair in seqs.windows(2) {
+            let (s1, s2) = (&pair[0], &pair[1]);
+            let ratio = gestalt_ratio_seq(s1, s2);
+            let quick = quick_ratio_seq(s1, s2);
+            assert!(ratio <= quick, "{:?} {:?}", s1, s2);
+            assert!(quick <= real_quick_ratio_seq(s1, s2), "{:?} {:?}", s1, s2);
+        }
+    }
+
+    #[test]
+    /// Expected matches are from the python3 difflib documentation
+    /// of get_close_matches.
+    fn close_matches_example() {
+        let words = ["ape", "apple", "peach", "puppy"];
+        assert_eq!(close_matches("appel", words, 3, 0.6), ["apple", "ape"]);
+        let keywords = [
+            "and", "as", "assert", "break", "class", "continue", "def", "del",
+        ];
+        assert_eq!(close_matches("wheel", keywords, 3, 0.6), Vec::<&str>::new());
+        assert_eq!(close_matches("del", keywords, 3, 0.6), ["del", "def"]);
+        assert_eq!(close_matches("appel", words, 1, 0.0), ["apple"]);
+        assert!(close_matches("appel", words, 0, 0.0).is_empty());
+        assert_eq!(
+            close_matches("abcd", ["abcx", "abcy", "abcw", "zzzz"], 3, 0.5),
+            ["abcy", "abcx", "abcw"]
+        );
+    }
+
+    #[test]
+    fn empty_inputs() {
+        assert_eq!(gestalt_ratio("", ""), 1.0);
+        assert_eq!(gestalt_ratio("", "abc"), 0.0);
+        assert_eq!(gestalt_ratio("abc", ""), 0.0);
+        assert_eq!(gestalt_ratio_seq::<u8>(&[], &[]), 1.0);
+        assert_eq!(gestalt_ratio_seq(&[1], &[]), 0.0);
+        assert_eq!(gestalt_ratio_seq_hashed::<u8>(&[], &[]), 1.0);
+        assert_eq!(quick_ratio("", ""), 1.0);
+        assert_eq!(real_quick_ratio("", ""), 1.0);
+        assert_eq!(GestaltMatcher::new("").ratio(""), 1.0);
+
+        assert_eq!(checked_gestalt_ratio("", ""), None);
+        assert_eq!(checked_gestalt_ratio("", "abc"), Some(0.0));
+        assert_eq!(checked_gestalt_ratio_seq::<u8>(&[], &[]), None);
+        assert_eq!(checked_gestalt_ratio_seq(&[], &[1]), Some(0.0));
+    }
+
+    #[test]
+    /// Expected values are from python3 difflib, with the same isjunk
+    /// predicates.
+    fn junk() {
+        let is_space = |g: &str| g == " ";
+        assert_eq!(
+            gestalt_ratio_with_junk(" abcd", "abcd abcd", is_space),
+            0.5714285714285714
+        );
+        assert_eq!(gestalt_ratio(" abcd", "abcd abcd"), 0.7142857142857143);
+        assert_eq!(
+            gestalt_ratio_with_junk(
+                "private Thread currentThread;",
+                "private volatile Thread currentThread;",
+                is_space
+            ),
+            0.8656716417910447
+        );
+        let matcher = GestaltMatcher::with_junk("abcd abcd", is_space);
+        assert_eq!(
+            matcher.matching_blocks(" abcd")[0],
+            Match {
+                a: 1,
+                b: 0,
+                size: 4
+            }
+        );
+
+        // Junk lines extend the match on "a", but can't start their own.
+        let s1 = ["a", "", "", "b"];
+        let s2 = ["", "", "b", "a", "", "", "c"];
+        assert_eq!(
+            gestalt_ratio_seq_with_junk(&s1, &s2, |l| l.is_empty()),
+            6.0 / 11.0
+        );
+        assert_eq!(gestalt_ratio_seq_hashed(&s1, &s2), 6.0 / 11.0);
+    }
+
+    #[test]
+    /// Expected values are from python3 difflib, where autojunk is on
+    /// by default.
+    fn autojunk() {
+        let b = "the quick brown fox jumps over the lazy dog. ".repeat(5);
+        let a = b.replace("quick", "slow").replace("dog", "cat");
+        let matcher = GestaltMatcher::new(&b);
+        assert_eq!(matcher.ratio(&a), 0.8314606741573034);
+        let matcher = matcher.autojunk(true);
+        assert_eq!(matcher.ratio(&a), 0.017977528089887642);
+
+        // Too short for anything to be popular.
+        let matcher = GestaltMatcher::new("Wikimania").autojunk(true);
+        assert_eq!(
+            matcher.ratio("Wikimedia"),
+            gestalt_ratio("Wikimedia", "Wikimania")
+        );
+    }
+
+    #[test]
+    fn symmetric_example() {
+        let s1 = "Ebojfm Mzpm";
+        let s2 = "Ebfo ef Mfpo";
+        assert_eq!(symmetric_gestalt_ratio(s1, s2), 0.6086956521739131);
+        assert_eq!(symmetric_gestalt_ratio(s2, s1), 0.6086956521739131);
+        assert_eq!(symmetric_gestalt_ratio("", ""), 1.0);
+    }
+
+    #[test]
+    fn symmetric_is_symmetric() {
+        let seqs = pseudo_random_seqs(12, 80, 3, 25);
+        for pair in seqs.windows(2) {
+            let (s1, s2) = (&pair[0], &pair[1]);
+            let forward = symmetric_gestalt_ratio_seq(s1, s2);
+            assert_eq!(
+                forward,
+                symmetric_gestalt_ratio_seq(s2, s1),
+                "{:?} {:?}",
+                s1,
+                s2
+            );
+            assert!(forward >= gestalt_ratio_seq(s1, s2));
+            assert!(forward >= gestalt_ratio_seq(s2, s1));
+
+            let s1: String = s1.iter().map(|&x| (b'a' + x) as char).collect();
+            let s2: String = s2.iter().map(|&x| (b'a' + x) as char).collect();
+            assert_eq!(
+                symmetric_gestalt_ratio(&s1, &s2),
+                symmetric_gestalt_ratio(&s2, &s1),
+                "{:?} {:?}",
+                s1,
+                s2
+            );
         }
     }
+
+    #[test]
+    fn tokenizers() {
+        let s1 = "the cat sat on the mat";
+        let s2 = "the cat sat on a mat";
+        assert_eq!(
+            gestalt_ratio_with(s1, s2, Tokenizer::Whitespace),
+            10.0 / 12.0
+        );
+        assert_eq!(
+            gestalt_ratio_with(s1, s2, Tokenizer::UnicodeWords),
+            10.0 / 12.0
+        );
+        assert_eq!(
+            gestalt_ratio_with("Hello, world!", "hello world", Tokenizer::UnicodeWords),
+            0.5
+        );
+        assert_eq!(
+            gestalt_ratio_with("a\nb\nc\n", "a\nx\nc", Tokenizer::Lines),
+            4.0 / 6.0
+        );
+        for tokenizer in [Tokenizer::Graphemes, Tokenizer::LegacyGraphemes] {
+            assert_eq!(
+                gestalt_ratio_with("x² + y²", "y² + z²", tokenizer),
+                gestalt_ratio("x² + y²", "y² + z²")
+            );
+        }
+
+        // "é" as "e" followed by a combining accent is one grapheme,
+        // two chars and three bytes.
+        let s1 = "cafe\u{301}";
+        let s2 = "cafe";
+        assert_eq!(gestalt_ratio_with(s1, s2, Tokenizer::Graphemes), 6.0 / 8.0);
+        assert_eq!(gestalt_ratio_with(s1, s2, Tokenizer::Chars), 8.0 / 9.0);
+        assert_eq!(gestalt_ratio_with(s1, s2, Tokenizer::Bytes), 8.0 / 10.0);
+        assert_eq!(Tokenizer::default(), Tokenizer::Graphemes);
+    }
+
+    #[test]
+    fn options() {
+        let nfc = Options {
+            normalization: Normalization::Nfc,
+            ..Options::default()
+        };
+        let folded = Options {
+            case_fold: true,
+            ..nfc
+        };
+        assert!(gestalt_ratio("Café", "cafe\u{301}") < 1.0);
+        assert!(gestalt_ratio_with_options("Café", "cafe\u{301}", &nfc) < 1.0);
+        assert_eq!(gestalt_ratio_with_options("café", "cafe\u{301}", &nfc), 1.0);
+        assert_eq!(
+            gestalt_ratio_with_options("Café", "cafe\u{301}", &folded),
+            1.0
+        );
+        assert_eq!(
+            gestalt_ratio_with_options("Straße", "STRASSE", &folded),
+            1.0
+        );
+
+        let stripped = Options {
+            strip_diacritics: true,
+            ..Options::default()
+        };
+        assert_eq!(
+            gestalt_ratio_with_options("crème brûlée", "creme brulee", &stripped),
+            1.0
+        );
+        assert_eq!(
+            gestalt_ratio_with_options("한국어", "한국어", &stripped),
+            1.0
+        );
+
+        let nfkc = Options {
+            normalization: Normalization::Nfkc,
+            ..Options::default()
+        };
+        assert_eq!(gestalt_ratio_with_options("ﬁle x²", "file x2", &nfkc), 1.0);
+
+        let words = Options {
+            tokenizer: Tokenizer::UnicodeWords,
+            ..folded
+        };
+        assert_eq!(
+            gestalt_ratio_with_options("Hello, World!", "hello world", &words),
+            1.0
+        );
+
+        assert_eq!(
+            gestalt_ratio_with_options("x² + y²", "y² + z²", &Options::default()),
+            gestalt_ratio("x² + y²", "y² + z²")
+        );
+    }
+
+    #[test]
+    fn custom_equivalence() {
+        let s1 = ["The", "Quick", "brown", "fox"];
+        let s2 = ["the", "quick", "Brown", "dog"];
+        assert_eq!(gestalt_ratio_seq(&s1, &s2), 0.0);
+        assert_eq!(
+            gestalt_ratio_by(&s1, &s2, |a, b| a.eq_ignore_ascii_case(b)),
+            0.75
+        );
+        assert_eq!(gestalt_ratio_by_key(&s1, &s2, |w| w.to_lowercase()), 0.75);
+
+        let f1 = [1.0, 2.0, 3.0, 4.0];
+        let f2 = [1.001, 2.001, 3.5, 3.999];
+        let close = |a: &f64, b: &f64| (a - b).abs() < 0.01;
+        assert_eq!(gestalt_ratio_by(&f1, &f2, close), 0.75);
+
+        // Different element types on each side.
+        let chars = ['a', 'b', 'c'];
+        let bytes = [b'a', b'x', b'c'];
+        assert_eq!(
+            gestalt_ratio_by(&chars, &bytes, |c, b| *c == *b as char),
+            2.0 / 3.0
+        );
+
+        // Plain equality gives the same results as gestalt_ratio_seq,
+        // ties included, whichever side is shorter.
+        let seqs = pseudo_random_seqs(3, 40, 3, 20);
+        for pair in seqs.windows(2) {
+            let (s1, s2) = (&pair[0], &pair[1]);
+            assert_eq!(
+                gestalt_ratio_by(s1, s2, |a, b| a == b),
+                gestalt_ratio_seq(s1, s2)
+            );
+        }
+    }
+
+    #[test]
+    fn weighted() {
+        let s1 = ["let", "x", "=", "foo", "(", ")", ";"];
+        let s2 = ["let", "y", "=", "bar", "(", ")", ";"];
+        let punctuation = |t: &&str| !t.chars().any(char::is_alphanumeric);
+        let weight = |t: &&str| if punctuation(t) { 0.1 } else { 1.0 };
+        assert_eq!(gestalt_ratio_seq(&s1, &s2), 5.0 / 7.0);
+        let ratio = gestalt_ratio_weighted(&s1, &s2, weight);
+        assert!((ratio - 2.8 / 6.8).abs() < 1e-12, "{}", ratio);
+
+        // The heaviest match wins over the longest one.
+        let s1 = ["a", ";", ";", ";", "b", "id"];
+        let s2 = ["id", "x", ";", ";", ";"];
+        let weights = |t: &&str| if *t == "id" { 5.0 } else { 1.0 };
+        let ratio = gestalt_ratio_weighted(&s1, &s2, weights);
+        assert_eq!(ratio, 10.0 / 19.0);
+        assert_eq!(gestalt_ratio_seq(&s1, &s2), 6.0 / 11.0);
+
+        assert_eq!(gestalt_ratio_weighted::<u8, _>(&[], &[], |_| 1.0), 1.0);
+        assert_eq!(gestalt_ratio_weighted(&[1], &[2], |_| 0.0), 1.0);
+        // A negative zero would compare equal, so compare the bits.
+        assert_eq!(
+            gestalt_ratio_weighted(&["a"], &["b"], |_| 1.0).to_bits(),
+            0.0f64.to_bits()
+        );
+
+        let seqs = pseudo_random_seqs(5, 40, 3, 20);
+        for pair in seqs.windows(2) {
+            let (s1, s2) = (&pair[0], &pair[1]);
+            assert_eq!(
+                gestalt_ratio_weighted(s1, s2, |_| 1.0),
+                gestalt_ratio_seq(s1, s2),
+                "{:?} {:?}",
+                s1,
+                s2
+            );
+        }
+    }
+
+    #[test]
+    fn min_block_len() {
+        let strict = Options {
+            min_block_len: 3,
+            ..Options::default()
+        };
+        // Only coincidental single letters in common.
+        assert!(gestalt_ratio("rubber duck", "cordless drill") > 0.0);
+        assert_eq!(
+            gestalt_ratio_with_options("rubber duck", "cordless drill", &strict),
+            0.0
+        );
+
+        // "Apple iPhone 1" and " Pro" both survive, but after those
+        // the "2" and "3" differ.
+        let s1 = "Apple iPhone 12 Pro";
+        let s2 = "Apple iPhone 13 Pro";
+        assert_eq!(gestalt_ratio_with_options(s1, s2, &strict), 36.0 / 38.0);
+
+        // "abc" and "de" match, but the lone "f" after them doesn't.
+        let options = Options {
+            min_block_len: 2,
+            ..Options::default()
+        };
+        assert_eq!(
+            gestalt_ratio_with_options("abcXdeYf", "abcZdeWf", &options),
+            10.0 / 16.0
+        );
+        assert_eq!(gestalt_ratio_with_options("ab", "ba", &options), 0.0);
+        for min_block_len in [0, 1] {
+            let options = Options {
+                min_block_len,
+                ..Options::default()
+            };
+            assert_eq!(
+                gestalt_ratio_with_options("Ebojfm Mzpm", "Ebfo ef Mfpo", &options),
+                gestalt_ratio("Ebojfm Mzpm", "Ebfo ef Mfpo")
+            );
+        }
+    }
+
+    #[test]
+    /// Every longest match here is a single element at the very start
+    /// of what's left, so a recursive search would go as deep as the
+    /// input is long. Run it on a small stack to make sure the search
+    /// doesn't recurse.
+    fn deep_input_does_not_overflow_stack() {
+        let n: u32 = 3000;
+        let s1: Vec<u32> = (0..n).collect();
+        let s2: Vec<u32> = (0..n)
+            .flat_map(|x| [x, n])
+            .take(2 * n as usize - 1)
+            .collect();
+        let ratio = std::thread::Builder::new()
+            .stack_size(128 * 1024)
+            .spawn(move || gestalt_ratio_seq_hashed(&s1, &s2))
+            .unwrap()
+            .join()
+            .unwrap();
+        assert_eq!(ratio, 2.0 * n as f64 / (3 * n - 1) as f64);
+    }
+
+    #[test]
+    fn ascii_fast_path() {
+        assert_eq!(
+            gestalt_ratio_bytes(b"Wikimedia", b"Wikimania"),
+            gestalt_ratio("Wikimedia", "Wikimania")
+        );
+        assert_eq!(gestalt_ratio_bytes(&[0xff, 0x00], &[0x00]), 2.0 / 3.0);
+
+        // "\r\n" is a single grapheme, so it must not take the byte
+        // path.
+        assert!(!graphemes_are_bytes("a\r\nb"));
+        assert_eq!(gestalt_ratio("a\r\n", "a\r"), 2.0 / 4.0);
+        assert_eq!(gestalt_ratio_bytes(b"a\r\n", b"a\r"), 4.0 / 5.0);
+
+        let alphabet = b"ab \r\n";
+        let seqs = pseudo_random_seqs(19, 60, alphabet.len() as u64, 100);
+        for pair in seqs.windows(2) {
+            let s1: String = pair[0]
+                .iter()
+                .map(|&x| alphabet[x as usize] as char)
+                .collect();
+            let s2: String = pair[1]
+                .iter()
+                .map(|&x| alphabet[x as usize] as char)
+                .collect();
+            assert_eq!(
+                gestalt_ratio(&s1, &s2),
+                gestalt_ratio_with(&s1, &s2, Tokenizer::Graphemes),
+                "{:?} {:?}",
+                s1,
+                s2
+            );
+            assert_eq!(matching_blocks(&s1, &s2), {
+                let g1 = graphemes(&s1);
+                let g2 = graphemes(&s2);
+                finish_blocks(raw_blocks_hashed::<_, _, str>(&g1, &g2), g1.len(), g2.len())
+            });
+        }
+    }
+
+    #[test]
+    fn ratio_at_least() {
+        let pairs = [
+            ("Wikimedia", "Wikimania"),
+            ("Ebojfm Mzpm", "Ebfo ef Mfpo"),
+            ("x² + y²", "y² + z²"),
+            ("", ""),
+            ("", "abc"),
+            ("abc", "xyz"),
+        ];
+        for (s1, s2) in pairs {
+            let ratio = gestalt_ratio(s1, s2);
+            for threshold in [0.0, 0.3, 0.6, 0.7142857142857143, 0.75, 0.8, 1.0] {
+                let expected = if ratio >= threshold {
+                    Some(ratio)
+                } else {
+                    None
+                };
+                assert_eq!(
+                    gestalt_ratio_at_least(s1, s2, threshold),
+                    expected,
+                    "{:?} {:?} {}",
+                    s1,
+                    s2,
+                    threshold
+                );
+            }
+        }
+    }
+
+    #[test]
+    /// The bound only ever decreases during the search, and once it
+    /// is too low the search stops.
+    fn ratio_at_least_stops_early() {
+        let s1 = "abcdefghijklmnopqrstuvwxyz".repeat(4);
+        let s2 = "zyxwvutsrqponmlkjihgfedcba".repeat(4);
+        let mut searches = 0;
+        let mut longest = |alo: usize, ahi: usize, blo: usize, bhi: usize| {
+            searches += 1;
+            let ((l1, r1), (l2, _)) = longest_common_subseq_idxs(
+                &s1.as_bytes()[alo..ahi],
+                &s2.as_bytes()[blo..bhi],
+                &u8::eq,
+            );
+            Match {
+                a: alo + l1,
+                b: blo + l2,
+                size: r1 - l1,
+            }
+        };
+        let total = s1.len() + s2.len();
+        let mut blocks = Vec::new();
+        let finished = collect_matching_blocks_while(
+            0,
+            s1.len(),
+            0,
+            s2.len(),
+            &mut longest,
+            &mut blocks,
+            |m| calculate_ratio(m, total) >= 0.5,
+        );
+        assert!(!finished);
+        assert!(searches < 5, "{}", searches);
+        assert_eq!(gestalt_ratio_at_least(&s1, &s2, 0.5), None);
+        assert!(gestalt_ratio(&s1, &s2) < 0.5);
+    }
+
+    const DIFF_A: &str =
+        "one\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\neleven\ntwelve\nthirteen\n";
+    const DIFF_B: &str = "zero\none\ntwo\nthree\nFOUR\nfive\nsix\nseven\neight\nnine\nten\neleven\nthirteen\nfourteen\n";
+
+    #[test]
+    /// Expected diffs are from python3 difflib, with the same
+    /// arguments.
+    fn unified_diff_example() {
+        let a: Vec<&str> = DIFF_A.split_inclusive('\n').collect();
+        let b: Vec<&str> = DIFF_B.split_inclusive('\n').collect();
+        let header = DiffHeader {
+            from_file: "a.txt",
+            to_file: "b.txt",
+            from_date: "2020-01-01",
+            to_date: "2020-01-02",
+        };
+        assert_eq!(
+            unified_diff(&a, &b, &header, 2).concat(),
+            "--- a.txt\t2020-01-01\n+++ b.txt\t2020-01-02\n@@ -1,6 +1,7 @@\n+zero\n one\n two\n three\n-four\n+FOUR\n five\n six\n@@ -10,4 +11,4 @@\n ten\n eleven\n-twelve\n thirteen\n+fourteen\n"
+        );
+        assert_eq!(
+            unified_diff(&a, &b, &DiffHeader::default(), 0).concat(),
+            "--- \n+++ \n@@ -0,0 +1 @@\n+zero\n@@ -4 +5 @@\n-four\n+FOUR\n@@ -12 +12,0 @@\n-twelve\n@@ -13,0 +14 @@\n+fourteen\n"
+        );
+        assert!(unified_diff(&a, &a, &header, 3).is_empty());
+
+        // A context of usize::MAX asks for every line.
+        assert_eq!(
+            unified_diff(
+                &["a\n", "b\n", "c\n"],
+                &["a\n", "x\n", "c\n"],
+                &DiffHeader::default(),
+                usize::MAX
+            )
+            .concat(),
+            "--- \n+++ \n@@ -1,3 +1,3 @@\n a\n-b\n+x\n c\n"
+        );
+    }
+
+    #[test]
+    fn context_diff_example() {
+        let a: Vec<&str> = DIFF_A.split_inclusive('\n').collect();
+        let b: Vec<&str> = DIFF_B.split_inclusive('\n').collect();
+        let header = DiffHeader {
+            from_file: "a.txt",
+            to_file: "b.txt",
+            ..DiffHeader::default()
+        };
+        assert_eq!(
+            context_diff(&a, &b, &header, 2).concat(),
+            "*** a.txt\n--- b.txt\n***************\n*** 1,6 ****\n  one\n  two\n  three\n! four\n  five\n  six\n--- 1,7 ----\n+ zero\n  one\n  two\n  three\n! FOUR\n  five\n  six\n***************\n*** 10,13 ****\n  ten\n  eleven\n- twelve\n  thirteen\n--- 11,14 ----\n  ten\n  eleven\n  thirteen\n+ fourteen\n"
+        );
+    }
+
+    #[test]
+    fn diff_without_trailing_newline() {
+        let a = ["same\n", "old"];
+        let b = ["same\n", "new\n"];
+        assert_eq!(
+            unified_diff(&a, &b, &DiffHeader::default(), 3).concat(),
+            "--- \n+++ \n@@ -1,2 +1,2 @@\n same\n-old\n\\ No newline at end of file\n+new\n"
+        );
+    }
+
+    #[test]
+    fn grouped_opcodes_example() {
+        let groups = grouped_opcodes(
+            &opcodes_seq(&[1, 2, 3, 4, 5, 6, 7, 8, 9], &[1, 2, 3, 4, 0, 6, 7, 8, 9]),
+            1,
+        );
+        assert_eq!(
+            groups,
+            vec![vec![
+                Opcode {
+                    tag: Tag::Equal,
+                    a: 3..4,
+                    b: 3..4
+                },
+                Opcode {
+                    tag: Tag::Replace,
+                    a: 4..5,
+                    b: 4..5
+                },
+                Opcode {
+                    tag: Tag::Equal,
+                    a: 5..6,
+                    b: 5..6
+                },
+            ]]
+        );
+        assert!(grouped_opcodes(&[], 3).is_empty());
+    }
+
+    #[test]
+    /// Expected deltas are from python3 `difflib.ndiff`.
+    fn ndiff_example() {
+        let delta = |a: &str, b: &str| {
+            let a: Vec<&str> = a.split_inclusive('\n').collect();
+            let b: Vec<&str> = b.split_inclusive('\n').collect();
+            ndiff(&a, &b).concat()
+        };
+        assert_eq!(
+            delta("one\ntwo\nthree\n", "ore\ntree\nemu\n"),
+            "- one\n?  ^\n+ ore\n?  ^\n- two\n- three\n?  -\n+ tree\n+ emu\n"
+        );
+        assert_eq!(
+            delta(
+                "\ttimeout = 30\nretries = 3\nhost = example.com\n",
+                "\ttimeout = 45\nretries = 3\nhost = example.org\nport = 8080\n"
+            ),
+            "- \ttimeout = 30\n? \t          ^^\n+ \ttimeout = 45\n? \t          ^^\n  retries = 3\n- host = example.com\n?                - ^\n+ host = example.org\n?                 ^^\n+ port = 8080\n"
+        );
+        assert_eq!(delta("a\nb\nc\n", "x\ny\n"), "+ x\n+ y\n- a\n- b\n- c\n");
+        assert_eq!(
+            delta("same\nkeep\nsame\n", "same\nother\nsame\n"),
+            "  same\n- keep\n+ other\n  same\n"
+        );
+    }
+
+    #[test]
+    fn restore_from_ndiff() {
+        let a: Vec<&str> = "one\ntwo\nthree".split_inclusive('\n').collect();
+        let b: Vec<&str> = "ore\ntree\nemu\nthree".split_inclusive('\n').collect();
+        let delta = ndiff(&a, &b);
+        assert_eq!(restore(&delta, Side::First), a);
+        assert_eq!(restore(&delta, Side::Second), b);
+
+        let line = |x: &u8| format!("line {}\n", x);
+        for pair in pseudo_random_seqs(23, 50, 6, 12).windows(2) {
+            let a: Vec<String> = pair[0].iter().map(line).collect();
+            let b: Vec<String> = pair[1].iter().map(line).collect();
+            let delta = ndiff(&a, &b);
+            assert_eq!(restore(&delta, Side::First), a);
+            assert_eq!(restore(&delta, Side::Second), b);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "html")]
+    fn html_table() {
+        let a = ["one\n", "two\n", "three\n"];
+        let b = ["ore\n", "tree\n", "emu\n"];
+        assert_eq!(
+            HtmlDiff::default().make_table(&a, &b, "a", "b"),
+            "<table class=\"diff\">\n\
+             <thead><tr><th colspan=\"2\" class=\"diff_header\">a</th><th colspan=\"2\" class=\"diff_header\">b</th></tr></thead>\n\
+             <tbody>\n\
+             <tr><td class=\"diff_header\">1</td><td nowrap=\"nowrap\"><span class=\"diff_sub\">one</span></td><td class=\"diff_header\">1</td><td nowrap=\"nowrap\"><span class=\"diff_add\">ore</span></td></tr>\n\
+             <tr><td class=\"diff_header\">2</td><td nowrap=\"nowrap\"><span class=\"diff_sub\">two</span></td><td class=\"diff_header\"></td><td nowrap=\"nowrap\"></td></tr>\n\
+             <tr><td class=\"diff_header\">3</td><td nowrap=\"nowrap\">t<span class=\"diff_sub\">h</span>ree</td><td class=\"diff_header\">2</td><td nowrap=\"nowrap\">tree</td></tr>\n\
+             <tr><td class=\"diff_header\"></td><td nowrap=\"nowrap\"></td><td class=\"diff_header\">3</td><td nowrap=\"nowrap\"><span class=\"diff_add\">emu</span></td></tr>\n\
+             </tbody>\n\
+             </table>\n"
+        );
+
+        let html = HtmlDiff {
+            tab_size: 4,
+            context: None,
+        }
+        .make_table(&["\tx<y"], &["\tx>y"], "", "");
+        assert!(!html.contains("<thead>"));
+        assert!(html.contains("&nbsp;&nbsp;&nbsp;&nbsp;x<span class=\"diff_chg\">&lt;</span>y"));
+    }
+
+    #[test]
+    #[cfg(feature = "html")]
+    fn html_context() {
+        let a: Vec<String> = (1..=30).map(|i| format!("line {}\n", i)).collect();
+        let mut b = a.clone();
+        b[9] = "changed\n".to_string();
+        b[24] = "line twenty-five\n".to_string();
+        let diff = HtmlDiff {
+            context: Some(2),
+            ..HtmlDiff::default()
+        };
+        let html = diff.make_table(&a, &b, "", "");
+        assert_eq!(html.matches("<tbody>").count(), 2);
+        assert_eq!(html.matches("<tr>").count(), 10);
+        assert!(html.contains(">8</td>") && html.contains(">12</td>"));
+        assert!(!html.contains(">7</td>") && !html.contains(">13</td>"));
+
+        assert!(diff
+            .make_table(&a, &a, "", "")
+            .contains("No Differences Found"));
+
+        // A context too large to add to still shows every row.
+        let everything = HtmlDiff {
+            context: Some(usize::MAX),
+            ..HtmlDiff::default()
+        }
+        .make_table(&a, &b, "", "");
+        assert_eq!(everything, HtmlDiff::default().make_table(&a, &b, "", ""));
+        assert!(everything.contains("changed"));
+        let file = diff.make_file(&a, &b, "a", "b");
+        assert!(
+            file.starts_with("<!DOCTYPE html>")
+                && file.contains(&html[html.find("<tbody>").unwrap()..])
+        );
+    }
+
+    #[test]
+    fn term_diff_highlights_words() {
+        let a = ["timeout = 30\n", "host = example.com\n", "same\n", "gone\n"];
+        let b = ["timeout = 45\n", "host = example.org\n", "same\n", "new\n"];
+        assert_eq!(
+            TermDiff::default().render(&a, &b),
+            "\x1b[31m- timeout = \x1b[7m30\x1b[0m\n\
+             \x1b[32m+ timeout = \x1b[7m45\x1b[0m\n\
+             \x1b[31m- host = \x1b[7mexample.com\x1b[0m\n\
+             \x1b[32m+ host = \x1b[7mexample.org\x1b[0m\n\
+             \x20 same\n\
+             \x1b[31m- gone\x1b[0m\n\
+             \x1b[32m+ new\x1b[0m\n"
+        );
+        let graphemes = TermDiff {
+            highlight: Highlight::Graphemes,
+            ..TermDiff::default()
+        };
+        assert_eq!(
+            graphemes.render(&["cafe\u{301} noir"], &["cafe\u{301} noi"]),
+            "\x1b[31m- cafe\u{301} noi\x1b[7mr\x1b[0m\n\x1b[32m+ cafe\u{301} noi\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn term_diff_wraps_by_width() {
+        let wrapped = TermDiff {
+            width: Some(10),
+            ..TermDiff::default()
+        };
+        // Wide characters take two columns, so four fit in the eight
+        // columns after the code.
+        assert_eq!(
+            wrapped.render(&["same\n"], &["same\n", "日本語のテキストです\n"]),
+            "  same\n\
+             \x1b[32m+ 日本語の\x1b[0m\n\
+             \x1b[32m  テキスト\x1b[0m\n\
+             \x1b[32m  です\x1b[0m\n"
+        );
+        // Tabs are expanded before wrapping, and a highlight carries
+        // on across the break.
+        assert_eq!(
+            wrapped.render(&["\tkey = old_value"], &["\tkey = new_value"]),
+            "\x1b[31m-         \x1b[0m\n\
+             \x1b[31m  key = \x1b[7mol\x1b[0m\n\
+             \x1b[31m  \x1b[7md_value\x1b[0m\n\
+             \x1b[32m+         \x1b[0m\n\
+             \x1b[32m  key = \x1b[7mne\x1b[0m\n\
+             \x1b[32m  \x1b[7mw_value\x1b[0m\n"
+        );
+    }
+}
+
+/// Finds the longest common substring of `s1` and `s2`, where
+/// elements are compared with `eq`, returning its range in each. If
+/// there are several, the one ending earliest in `s1` wins, and then
+/// the one ending earliest in `s2`.
+fn longest_common_subseq_idxs<A, B, F>(
+    s1: &[A],
+    s2: &[B],
+    eq: &F,
+) -> ((usize, usize), (usize, usize))
+where
+    F: Fn(&A, &B) -> bool,
+{
+    let (max_length, ending_index_1, ending_index_2) = if s2.len() <= s1.len() {
+        longest_common_run(s1, s2, eq, |i, j| (i, j))
+    } else {
+        let (max_length, ending_index_2, ending_index_1) =
+            longest_common_run(s2, s1, &|c2: &B, c1: &A| eq(c1, c2), |j, i| (i, j));
+        (max_length, ending_index_1, ending_index_2)
+    };
     (
         (ending_index_1 - max_length, ending_index_1),
         (ending_index_2 - max_length, ending_index_2),
     )
 }
-fn matching_items<T: Eq>(s1: &[T], s2: &[T]) -> usize {
-    let ((l1, r1), (l2, r2)) = longest_common_subseq_idxs(s1, s2);
-    assert_eq!(r1 - l1, r2 - l2);
-    if l1 == r1 {
-        0
+
+/// Dynamic programming search for the longest common run, returning
+/// its length and its ending index in `outer` and `inner`. Only two
+/// rows of the table are kept, each the length of `inner`, so callers
+/// should pass the shorter sequence as `inner`. Since that may mean
+/// the rows run over `s1` instead of `s2`, ties are broken by `order`,
+/// which maps a pair of ending indices to the key to minimize.
+fn longest_common_run<O, I, F, K>(
+    outer: &[O],
+    inner: &[I],
+    eq: &F,
+    order: impl Fn(usize, usize) -> K,
+) -> (usize, usize, usize)
+where
+    F: Fn(&O, &I) -> bool,
+    K: Ord,
+{
+    let mut max_length = 0;
+    let mut ending_outer = outer.len();
+    let mut ending_inner = inner.len();
+    let mut prev = vec![0; inner.len() + 1];
+    let mut cur = vec![0; inner.len() + 1];
+
+    for (i, c1) in outer.iter().enumerate() {
+        for (j, c2) in inner.iter().enumerate() {
+            cur[j + 1] = if eq(c1, c2) { prev[j] + 1 } else { 0 };
+            let length = cur[j + 1];
+            if length > max_length
+                || (length == max_length
+                    && length > 0
+                    && order(i + 1, j + 1) < order(ending_outer, ending_inner))
+            {
+                max_length = length;
+                ending_outer = i + 1;
+                ending_inner = j + 1;
+            }
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    (max_length, ending_outer, ending_inner)
+}
+
+/// Pending work for [`collect_matching_blocks`].
+enum Work {
+    /// Search `s1[alo..ahi]` and `s2[blo..bhi]` for matching blocks.
+    Search(usize, usize, usize, usize),
+    /// Output a block found by an earlier search.
+    Emit(Match),
+}
+
+/// Collects the matching blocks between `s1[alo..ahi]` and
+/// `s2[blo..bhi]` into `blocks`, in increasing order, by finding the
+/// longest common substring with `longest` and then doing the same
+/// for the pieces to either side of it.
+///
+/// The pieces are kept on an explicit stack rather than recursed
+/// on, since the number of pieces waiting can grow with the length
+/// of the input.
+fn collect_matching_blocks<F>(
+    alo: usize,
+    ahi: usize,
+    blo: usize,
+    bhi: usize,
+    longest: &mut F,
+    blocks: &mut Vec<Match>,
+) where
+    F: FnMut(usize, usize, usize, usize) -> Match,
+{
+    collect_matching_blocks_while(alo, ahi, blo, bhi, longest, blocks, |_| true);
+}
+
+/// Like [`collect_matching_blocks`], but gives up and returns false
+/// as soon as `viable` rejects the most elements that could still
+/// end up matching: those matched so far, plus the length of the
+/// shorter side of every piece still waiting to be searched.
+fn collect_matching_blocks_while<F, V>(
+    alo: usize,
+    ahi: usize,
+    blo: usize,
+    bhi: usize,
+    longest: &mut F,
+    blocks: &mut Vec<Match>,
+    viable: V,
+) -> bool
+where
+    F: FnMut(usize, usize, usize, usize) -> Match,
+    V: Fn(usize) -> bool,
+{
+    let potential = |alo: usize, ahi: usize, blo: usize, bhi: usize| (ahi - alo).min(bhi - blo);
+    let mut bound = potential(alo, ahi, blo, bhi);
+    let mut stack = vec![Work::Search(alo, ahi, blo, bhi)];
+    while let Some(work) = stack.pop() {
+        match work {
+            Work::Emit(m) => blocks.push(m),
+            Work::Search(alo, ahi, blo, bhi) => {
+                bound -= potential(alo, ahi, blo, bhi);
+                let m = longest(alo, ahi, blo, bhi);
+                if m.size > 0 {
+                    bound += m.size;
+                    // Pushed in reverse, so the left piece is handled
+                    // first and the blocks come out in order.
+                    if m.a + m.size < ahi && m.b + m.size < bhi {
+                        bound += potential(m.a + m.size, ahi, m.b + m.size, bhi);
+                        stack.push(Work::Search(m.a + m.size, ahi, m.b + m.size, bhi));
+                    }
+                    stack.push(Work::Emit(m));
+                    if alo < m.a && blo < m.b {
+                        bound += potential(alo, m.a, blo, m.b);
+                        stack.push(Work::Search(alo, m.a, blo, m.b));
+                    }
+                }
+                if !viable(bound) {
+                    return false;
+                }
+            }
+        }
+    }
+    true
+}
+
+/// The matching blocks of two sequences, without merging or the
+/// sentinel, found with the dynamic programming search.
+fn raw_blocks_seq<T: Eq>(s1: &[T], s2: &[T]) -> Vec<Match> {
+    raw_blocks_by(s1, s2, &T::eq)
+}
+
+/// The matching blocks of two sequences whose elements are compared
+/// with `eq`, without merging or the sentinel.
+fn raw_blocks_by<A, B, F>(s1: &[A], s2: &[B], eq: &F) -> Vec<Match>
+where
+    F: Fn(&A, &B) -> bool,
+{
+    let mut blocks = Vec::new();
+    let mut longest = |alo: usize, ahi: usize, blo: usize, bhi: usize| {
+        let ((l1, r1), (l2, r2)) = longest_common_subseq_idxs(&s1[alo..ahi], &s2[blo..bhi], eq);
+        assert_eq!(r1 - l1, r2 - l2);
+        Match {
+            a: alo + l1,
+            b: blo + l2,
+            size: r1 - l1,
+        }
+    };
+    collect_matching_blocks(0, s1.len(), 0, s2.len(), &mut longest, &mut blocks);
+    blocks
+}
+
+/// The matching blocks of two sequences, without merging or the
+/// sentinel, found with the hash index over `s2`.
+fn raw_blocks_hashed<A, B, T>(s1: &[A], s2: &[B]) -> Vec<Match>
+where
+    A: Borrow<T>,
+    B: Borrow<T>,
+    T: ?Sized + Hash + Eq,
+{
+    raw_blocks_indexed(s1, &Index::new(s2.iter().map(Borrow::borrow)))
+}
+
+/// The matching blocks between `s1` and the sequence `index` was
+/// built from, without merging or the sentinel.
+fn raw_blocks_indexed<A, T>(s1: &[A], index: &Index<T>) -> Vec<Match>
+where
+    A: Borrow<T>,
+    T: ?Sized + Hash + Eq,
+{
+    let mut blocks = Vec::new();
+    let mut longest = |alo, ahi, blo, bhi| index.longest_match(s1, alo, ahi, blo, bhi);
+    collect_matching_blocks(0, s1.len(), 0, index.len(), &mut longest, &mut blocks);
+    blocks
+}
+
+/// The ratio of twice the matched elements to the total number of
+/// elements in both sequences. Two empty sequences are identical, so
+/// they score 1, as in Python's difflib.
+fn calculate_ratio(matches: usize, length: usize) -> f64 {
+    if length == 0 {
+        1.0
     } else {
-        let left_rec = if l1 > 0 && l2 > 0 {
-            matching_items(&s1[..l1], &s2[..l2])
-        } else {
-            0
-        };
-        let right_rec = if r1 < s1.len() && r2 < s2.len() {
-            matching_items(&s1[r1..], &s2[r2..])
+        (2.0 * matches as f64) / (length as f64)
+    }
+}
+
+fn matching_items(blocks: &[Match]) -> usize {
+    blocks.iter().map(|m| m.size).sum()
+}
+
+/// Merges adjacent blocks and appends the sentinel, for the public
+/// matching block functions.
+fn finish_blocks(raw: Vec<Match>, len1: usize, len2: usize) -> Vec<Match> {
+    let mut blocks: Vec<Match> = Vec::with_capacity(raw.len() + 1);
+    for m in raw {
+        match blocks.last_mut() {
+            Some(last) if last.a + last.size == m.a && last.b + last.size == m.b => {
+                last.size += m.size;
+            }
+            _ => blocks.push(m),
+        }
+    }
+    blocks.push(Match {
+        a: len1,
+        b: len2,
+        size: 0,
+    });
+    blocks
+}
+
+/// Splits a string into the extended graphemes that are compared by
+/// the string functions of this crate.
+fn graphemes(s: &str) -> Vec<&str> {
+    UnicodeSegmentation::graphemes(s, true).collect()
+}
+
+/// Whether every grapheme of `s` is a single byte, so that comparing
+/// bytes gives the same result as comparing graphemes without the
+/// cost of segmenting. This is true of ASCII text, except that a
+/// carriage return followed by a line feed is one grapheme.
+fn graphemes_are_bytes(s: &str) -> bool {
+    s.is_ascii() && !s.contains("\r\n")
+}
+
+/// A pair of identical runs in two sequences: `s1[a..a + size]`
+/// equals `s2[b..b + size]`. This mirrors the `Match` tuple returned
+/// by Python's `SequenceMatcher.get_matching_blocks`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Match {
+    /// Start of the run in the first sequence.
+    pub a: usize,
+    /// Start of the run in the second sequence.
+    pub b: usize,
+    /// Length of the run.
+    pub size: usize,
+}
+
+/// Returns the blocks of graphemes that the gestalt ratio counts as
+/// matching between two strings, in increasing order. Indices are
+/// grapheme indices, not byte offsets. Like Python's
+/// `get_matching_blocks`, the list always ends with a zero-sized
+/// sentinel `Match { a: len1, b: len2, size: 0 }`, and adjacent
+/// blocks are merged into one.
+pub fn matching_blocks(s1: &str, s2: &str) -> Vec<Match> {
+    if graphemes_are_bytes(s1) && graphemes_are_bytes(s2) {
+        let raw = raw_blocks_hashed::<_, _, u8>(s1.as_bytes(), s2.as_bytes());
+        return finish_blocks(raw, s1.len(), s2.len());
+    }
+    let s1_graphemes = graphemes(s1);
+    let s2_graphemes = graphemes(s2);
+    let raw = raw_blocks_hashed::<_, _, str>(&s1_graphemes, &s2_graphemes);
+    finish_blocks(raw, s1_graphemes.len(), s2_graphemes.len())
+}
+
+/// Returns the matching blocks between two arbitrary sequences. See
+/// [`matching_blocks`] for the layout of the result.
+pub fn matching_blocks_seq<T: Eq>(s1: &[T], s2: &[T]) -> Vec<Match> {
+    finish_blocks(raw_blocks_seq(s1, s2), s1.len(), s2.len())
+}
+
+/// The kind of edit described by an [`Opcode`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Tag {
+    /// `s1[a]` and `s2[b]` are identical.
+    Equal,
+    /// `s1[a]` should be replaced by `s2[b]`.
+    Replace,
+    /// `s2[b]` should be inserted at `s1[a.start]`; `a` is empty.
+    Insert,
+    /// `s1[a]` should be deleted; `b` is empty.
+    Delete,
+}
+
+/// One step in turning the first sequence into the second, in the
+/// style of Python's `SequenceMatcher.get_opcodes`. `a` and `b` are
+/// ranges into the first and second sequence respectively.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Opcode {
+    /// Kind of edit.
+    pub tag: Tag,
+    /// Range in the first sequence.
+    pub a: Range<usize>,
+    /// Range in the second sequence.
+    pub b: Range<usize>,
+}
+
+/// Converts a list of matching blocks, ending in the sentinel, into
+/// the opcodes that cover both sequences from start to end.
+fn opcodes_from_blocks(blocks: &[Match]) -> Vec<Opcode> {
+    let mut i = 0;
+    let mut j = 0;
+    let mut opcodes = Vec::new();
+    for m in blocks {
+        let tag = if i < m.a && j < m.b {
+            Some(Tag::Replace)
+        } else if i < m.a {
+            Some(Tag::Delete)
+        } else if j < m.b {
+            Some(Tag::Insert)
         } else {
-            0
+            None
         };
-        left_rec + (r1 - l1) + right_rec
+        if let Some(tag) = tag {
+            opcodes.push(Opcode {
+                tag,
+                a: i..m.a,
+                b: j..m.b,
+            });
+        }
+        i = m.a + m.size;
+        j = m.b + m.size;
+        if m.size > 0 {
+            opcodes.push(Opcode {
+                tag: Tag::Equal,
+                a: m.a..i,
+                b: m.b..j,
+            });
+        }
     }
+    opcodes
+}
+
+/// Returns the edits that turn `s1` into `s2`, derived from the same
+/// matching blocks that the gestalt ratio is computed from. Ranges
+/// are in graphemes, not bytes. Consecutive opcodes are contiguous,
+/// and together they cover both strings entirely.
+pub fn opcodes(s1: &str, s2: &str) -> Vec<Opcode> {
+    opcodes_from_blocks(&matching_blocks(s1, s2))
+}
+
+/// Returns the edits that turn one arbitrary sequence into another.
+/// See [`opcodes`].
+pub fn opcodes_seq<T: Eq>(s1: &[T], s2: &[T]) -> Vec<Opcode> {
+    opcodes_from_blocks(&matching_blocks_seq(s1, s2))
 }
 
 /// Ratcliff-Obershelp String Matching, otherwise known as Gestalt
@@ -107,11 +1397,115 @@ fn matching_items<T: Eq>(s1: &[T], s2: &[T]) -> usize {
 /// between two strings, based on recursively looking at longest
 /// common substrings. It is described in this wikipedia page:
 /// https://en.wikipedia.org/wiki/Gestalt_Pattern_Matching
+///
+/// Two empty strings score 1.0, like in Python's difflib, rather than
+/// the 0/0 of the formula. Use [`checked_gestalt_ratio`] to handle
+/// that case separately.
 pub fn gestalt_ratio(s1: &str, s2: &str) -> f64 {
-    let s1_graphemes: Vec<&str> = UnicodeSegmentation::graphemes(s1, true).collect();
-    let s2_graphemes: Vec<&str> = UnicodeSegmentation::graphemes(s2, true).collect();
-    (2.0 * matching_items(&s1_graphemes, &s2_graphemes) as f64)
-        / ((s1_graphemes.len() + s2_graphemes.len()) as f64)
+    if graphemes_are_bytes(s1) && graphemes_are_bytes(s2) {
+        return gestalt_ratio_bytes(s1.as_bytes(), s2.as_bytes());
+    }
+    let s1_graphemes = graphemes(s1);
+    let s2_graphemes = graphemes(s2);
+    let raw = raw_blocks_hashed::<_, _, str>(&s1_graphemes, &s2_graphemes);
+    calculate_ratio(
+        matching_items(&raw),
+        s1_graphemes.len() + s2_graphemes.len(),
+    )
+}
+
+/// The largest table, in cells, that [`gestalt_ratio_bytes`] searches
+/// with dynamic programming instead of a hash index.
+const SMALL_TABLE: usize = 4096;
+
+/// The gestalt ratio of two byte strings, comparing byte by byte.
+/// This skips grapheme segmentation entirely, so it is the fastest
+/// way to compare ASCII identifiers and other byte-oriented data.
+/// [`gestalt_ratio`] uses it automatically when both strings are
+/// ASCII.
+pub fn gestalt_ratio_bytes(s1: &[u8], s2: &[u8]) -> f64 {
+    // Both searches give the same blocks. For short inputs the table
+    // is cheaper than building and probing the index.
+    let raw = if s1.len().saturating_mul(s2.len()) <= SMALL_TABLE {
+        raw_blocks_seq(s1, s2)
+    } else {
+        raw_blocks_hashed::<_, _, u8>(s1, s2)
+    };
+    calculate_ratio(matching_items(&raw), s1.len() + s2.len())
+}
+
+/// Returns the [`gestalt_ratio`] of two strings if it is at least
+/// `threshold`, and `None` otherwise. This is faster than checking
+/// the result of `gestalt_ratio`, because it gives up as soon as
+/// the threshold is out of reach: first by comparing lengths, then
+/// by counting the graphemes the strings have in common, and then
+/// during the search, once too little is left unmatched.
+pub fn gestalt_ratio_at_least(s1: &str, s2: &str, threshold: f64) -> Option<f64> {
+    if graphemes_are_bytes(s1) && graphemes_are_bytes(s2) {
+        return ratio_at_least::<_, u8>(s1.as_bytes(), s2.as_bytes(), threshold);
+    }
+    ratio_at_least::<_, str>(&graphemes(s1), &graphemes(s2), threshold)
+}
+
+/// The indexed search behind [`gestalt_ratio_at_least`].
+fn ratio_at_least<A, T>(s1: &[A], s2: &[A], threshold: f64) -> Option<f64>
+where
+    A: Borrow<T>,
+    T: ?Sized + Hash + Eq,
+{
+    let total = s1.len() + s2.len();
+    let viable = |matches| calculate_ratio(matches, total) >= threshold;
+    if !viable(s1.len().min(s2.len())) {
+        return None;
+    }
+    let index = Index::new(s2.iter().map(Borrow::borrow));
+    if !viable(index.common_items(s1)) {
+        return None;
+    }
+    let mut blocks = Vec::new();
+    let mut longest = |alo, ahi, blo, bhi| index.longest_match(s1, alo, ahi, blo, bhi);
+    if !collect_matching_blocks_while(0, s1.len(), 0, s2.len(), &mut longest, &mut blocks, viable) {
+        return None;
+    }
+    Some(calculate_ratio(matching_items(&blocks), total))
+}
+
+/// Like [`gestalt_ratio`], but with the strings split into elements
+/// by `tokenizer` instead of always into graphemes. For example,
+/// [`Tokenizer::UnicodeWords`] scores sentences by the words they
+/// have in common, and [`Tokenizer::Lines`] scores files by lines.
+pub fn gestalt_ratio_with(s1: &str, s2: &str, tokenizer: Tokenizer) -> f64 {
+    gestalt_ratio_seq_hashed(&tokenizer.tokenize(s1), &tokenizer.tokenize(s2))
+}
+
+/// Like [`gestalt_ratio`], but with the strings normalized, case
+/// folded and split as set in `options`. For example, with
+/// [`Normalization::Nfc`] and case folding, "Café" and "cafe\u{301}"
+/// score 1.0, while `gestalt_ratio` sees two different graphemes.
+pub fn gestalt_ratio_with_options(s1: &str, s2: &str, options: &Options) -> f64 {
+    let (s1, s2) = (options.prepare(s1), options.prepare(s2));
+    let tokens1 = options.tokenizer.tokenize(&s1);
+    let tokens2 = options.tokenizer.tokenize(&s2);
+    let index = Index::new(tokens2.iter().copied());
+
+    let mut blocks = Vec::new();
+    let mut longest = |alo, ahi, blo, bhi| {
+        let m = index.longest_match(&tokens1, alo, ahi, blo, bhi);
+        if m.size < options.min_block_len {
+            Match { size: 0, ..m }
+        } else {
+            m
+        }
+    };
+    collect_matching_blocks(
+        0,
+        tokens1.len(),
+        0,
+        tokens2.len(),
+        &mut longest,
+        &mut blocks,
+    );
+    calculate_ratio(matching_items(&blocks), tokens1.len() + tokens2.len())
 }
 
 /// Ratcliff-Obershelp String Matching, otherwise known as Gestalt
@@ -119,6 +1513,216 @@ pub fn gestalt_ratio(s1: &str, s2: &str) -> f64 {
 /// between two strings, based on recursively looking at longest
 /// common substrings. It is described in this wikipedia page:
 /// https://en.wikipedia.org/wiki/Gestalt_Pattern_Matching
+///
+/// Two empty sequences score 1.0. Use [`checked_gestalt_ratio_seq`]
+/// to handle that case separately.
 pub fn gestalt_ratio_seq<T: Eq>(s1: &[T], s2: &[T]) -> f64 {
-    (2.0 * matching_items(s1, s2) as f64) / ((s1.len() + s2.len()) as f64)
+    calculate_ratio(matching_items(&raw_blocks_seq(s1, s2)), s1.len() + s2.len())
+}
+
+/// A symmetric version of [`gestalt_ratio`]:
+/// `symmetric_gestalt_ratio(a, b) == symmetric_gestalt_ratio(b, a)`
+/// for all strings, which makes it usable as a similarity for
+/// deduplication and clustering.
+///
+/// The plain ratio depends on argument order, because when two
+/// longest common substrings tie, the choice of which to recurse
+/// around depends on which string comes first. This computes the
+/// ratio in both orders and returns the larger, so it is never lower
+/// than [`gestalt_ratio`] and costs about twice as much.
+pub fn symmetric_gestalt_ratio(s1: &str, s2: &str) -> f64 {
+    let s1_graphemes = graphemes(s1);
+    let s2_graphemes = graphemes(s2);
+    let forward = raw_blocks_hashed::<_, _, str>(&s1_graphemes, &s2_graphemes);
+    let backward = raw_blocks_hashed::<_, _, str>(&s2_graphemes, &s1_graphemes);
+    calculate_ratio(
+        matching_items(&forward).max(matching_items(&backward)),
+        s1_graphemes.len() + s2_graphemes.len(),
+    )
+}
+
+/// A symmetric version of [`gestalt_ratio_seq`]. See
+/// [`symmetric_gestalt_ratio`].
+pub fn symmetric_gestalt_ratio_seq<T: Eq>(s1: &[T], s2: &[T]) -> f64 {
+    let forward = matching_items(&raw_blocks_seq(s1, s2));
+    let backward = matching_items(&raw_blocks_seq(s2, s1));
+    calculate_ratio(forward.max(backward), s1.len() + s2.len())
+}
+
+/// Like [`gestalt_ratio`], but returns `None` instead of a score when
+/// both strings are empty, since the ratio isn't really defined then.
+pub fn checked_gestalt_ratio(s1: &str, s2: &str) -> Option<f64> {
+    if s1.is_empty() && s2.is_empty() {
+        None
+    } else {
+        Some(gestalt_ratio(s1, s2))
+    }
+}
+
+/// Like [`gestalt_ratio_seq`], but returns `None` instead of a score
+/// when both sequences are empty.
+pub fn checked_gestalt_ratio_seq<T: Eq>(s1: &[T], s2: &[T]) -> Option<f64> {
+    if s1.is_empty() && s2.is_empty() {
+        None
+    } else {
+        Some(gestalt_ratio_seq(s1, s2))
+    }
+}
+
+/// Like [`gestalt_ratio_seq`], but elements are compared with `eq`
+/// instead of `==`, for example to compare tokens ignoring case
+/// without making lowercase copies of them. The two sequences don't
+/// even need to hold the same type.
+///
+/// `eq` is called on every pair of elements in the ranges being
+/// searched, so it should be cheap. It doesn't need to be transitive,
+/// so "within a tolerance" comparisons of floats work too.
+pub fn gestalt_ratio_by<A, B, F>(s1: &[A], s2: &[B], eq: F) -> f64
+where
+    F: Fn(&A, &B) -> bool,
+{
+    calculate_ratio(
+        matching_items(&raw_blocks_by(s1, s2, &eq)),
+        s1.len() + s2.len(),
+    )
+}
+
+/// Like [`gestalt_ratio_seq`], but elements are compared by the keys
+/// `key` extracts from them. `key` is called once per element, not
+/// once per comparison.
+pub fn gestalt_ratio_by_key<T, K, F>(s1: &[T], s2: &[T], key: F) -> f64
+where
+    K: Eq,
+    F: Fn(&T) -> K,
+{
+    let keys1: Vec<K> = s1.iter().map(&key).collect();
+    let keys2: Vec<K> = s2.iter().map(&key).collect();
+    gestalt_ratio_seq(&keys1, &keys2)
+}
+
+/// The same score as [`gestalt_ratio_seq`], for elements that can be
+/// hashed. Instead of comparing every pair of elements, this indexes
+/// the positions of each element of `s2`, like Python's
+/// `SequenceMatcher` does, which is much faster when the alphabet is
+/// large, such as when comparing lines of text. The result is always
+/// identical to that of [`gestalt_ratio_seq`].
+pub fn gestalt_ratio_seq_hashed<T: Hash + Eq>(s1: &[T], s2: &[T]) -> f64 {
+    let raw = raw_blocks_hashed::<_, _, T>(s1, s2);
+    calculate_ratio(matching_items(&raw), s1.len() + s2.len())
+}
+
+/// Like [`gestalt_ratio`], but graphemes of `s2` that `isjunk`
+/// accepts, such as whitespace, can't anchor a match. This mirrors
+/// the `isjunk` argument of Python's `SequenceMatcher`: each longest
+/// match is found among non-junk graphemes only, and then extended
+/// over any identical junk on either side of it. Junk still counts
+/// towards the length of the strings.
+pub fn gestalt_ratio_with_junk<F>(s1: &str, s2: &str, isjunk: F) -> f64
+where
+    F: Fn(&str) -> bool,
+{
+    let s1_graphemes = graphemes(s1);
+    let s2_graphemes = graphemes(s2);
+    let index = Index::with_junk(s2_graphemes.iter().copied(), isjunk);
+    calculate_ratio(
+        matching_items(&raw_blocks_indexed(&s1_graphemes, &index)),
+        s1_graphemes.len() + s2_graphemes.len(),
+    )
+}
+
+/// Like [`gestalt_ratio_seq_hashed`], but elements of `s2` that
+/// `isjunk` accepts, such as blank lines, can't anchor a match. See
+/// [`gestalt_ratio_with_junk`].
+pub fn gestalt_ratio_seq_with_junk<T, F>(s1: &[T], s2: &[T], isjunk: F) -> f64
+where
+    T: Hash + Eq,
+    F: Fn(&T) -> bool,
+{
+    let index = Index::with_junk(s2.iter(), isjunk);
+    calculate_ratio(
+        matching_items(&raw_blocks_indexed(s1, &index)),
+        s1.len() + s2.len(),
+    )
+}
+
+/// An upper bound on [`gestalt_ratio`] that is much cheaper to
+/// compute: the ratio counts every grapheme the two strings have in
+/// common, regardless of order. This is Python's
+/// `SequenceMatcher.quick_ratio`.
+pub fn quick_ratio(s1: &str, s2: &str) -> f64 {
+    let s1_graphemes = graphemes(s1);
+    let s2_graphemes = graphemes(s2);
+    let index = Index::new(s2_graphemes.iter().copied());
+    calculate_ratio(
+        index.common_items(&s1_graphemes),
+        s1_graphemes.len() + s2_graphemes.len(),
+    )
+}
+
+/// An upper bound on [`gestalt_ratio_seq`], counting the elements the
+/// sequences have in common regardless of order. See [`quick_ratio`].
+pub fn quick_ratio_seq<T: Hash + Eq>(s1: &[T], s2: &[T]) -> f64 {
+    let index = Index::new(s2.iter());
+    calculate_ratio(index.common_items(s1), s1.len() + s2.len())
+}
+
+/// An upper bound on [`gestalt_ratio`] and [`quick_ratio`] that only
+/// looks at the number of graphemes in each string, as if the shorter
+/// string matched the longer one entirely. This is Python's
+/// `SequenceMatcher.real_quick_ratio`.
+pub fn real_quick_ratio(s1: &str, s2: &str) -> f64 {
+    let len1 = UnicodeSegmentation::graphemes(s1, true).count();
+    let len2 = UnicodeSegmentation::graphemes(s2, true).count();
+    calculate_ratio(len1.min(len2), len1 + len2)
+}
+
+/// An upper bound on [`gestalt_ratio_seq`] and [`quick_ratio_seq`]
+/// that only looks at the lengths of the sequences. See
+/// [`real_quick_ratio`].
+pub fn real_quick_ratio_seq<T>(s1: &[T], s2: &[T]) -> f64 {
+    calculate_ratio(s1.len().min(s2.len()), s1.len() + s2.len())
+}
+
+/// Returns the best `n` of `candidates` that score at least `cutoff`
+/// against `word`, best first, like Python's
+/// `difflib.get_close_matches`. As in Python, candidates that score
+/// the same are returned in descending order of the candidates
+/// themselves.
+///
+/// Each candidate is scored as `gestalt_ratio(candidate, word)`.
+/// Since `word` is shared it is only segmented and indexed once, and
+/// the cheap upper bounds [`real_quick_ratio`] and [`quick_ratio`]
+/// are used to skip candidates that can't reach `cutoff` before the
+/// full ratio is computed.
+///
+/// # Panics
+///
+/// Panics if `cutoff` is not between 0 and 1.
+pub fn close_matches<'c, I>(word: &str, candidates: I, n: usize, cutoff: f64) -> Vec<&'c str>
+where
+    I: IntoIterator<Item = &'c str>,
+{
+    assert!(
+        (0.0..=1.0).contains(&cutoff),
+        "cutoff must be in [0.0, 1.0]: {}",
+        cutoff
+    );
+    let matcher = GestaltMatcher::new(word);
+    let mut scored: Vec<(f64, &str)> = Vec::new();
+    for candidate in candidates {
+        if matcher.real_quick_ratio(candidate) >= cutoff && matcher.quick_ratio(candidate) >= cutoff
+        {
+            let score = matcher.ratio(candidate);
+            if score >= cutoff {
+                scored.push((score, candidate));
+            }
+        }
+    }
+    scored.sort_by(|x, y| {
+        y.0.partial_cmp(&x.0)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| y.1.cmp(x.1))
+    });
+    scored.truncate(n);
+    scored.into_iter().map(|(_, candidate)| candidate).collect()
 }
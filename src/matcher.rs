@@ -0,0 +1,88 @@
+//! Comparing many strings against one fixed string.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::index::Index;
+use crate::{calculate_ratio, finish_blocks, graphemes, matching_items, raw_blocks_indexed, Match};
+
+/// Compares many strings against one fixed string, doing the work
+/// that only depends on the fixed string once. This is the
+/// equivalent of calling `set_seq2` on a Python `SequenceMatcher`
+/// and then `set_seq1` for every candidate.
+///
+/// The fixed string is segmented into graphemes and indexed when the
+/// matcher is created, so each comparison only has to segment the
+/// other string. Since the gestalt ratio is not symmetric, note that
+/// the fixed string is always the *second* argument:
+/// `GestaltMatcher::new(b).ratio(a)` is `gestalt_ratio(a, b)`.
+pub struct GestaltMatcher<'a> {
+    graphemes: Vec<&'a str>,
+    index: Index<'a, str>,
+}
+
+impl<'a> GestaltMatcher<'a> {
+    /// Creates a matcher that compares strings against `s`.
+    pub fn new(s: &'a str) -> Self {
+        GestaltMatcher::with_junk(s, |_| false)
+    }
+
+    /// Creates a matcher that compares strings against `s`, where the
+    /// graphemes of `s` that `isjunk` accepts can't anchor a match.
+    /// See [`gestalt_ratio_with_junk`](crate::gestalt_ratio_with_junk).
+    pub fn with_junk<F: Fn(&str) -> bool>(s: &'a str, isjunk: F) -> Self {
+        let graphemes = graphemes(s);
+        let index = Index::with_junk(graphemes.iter().copied(), isjunk);
+        GestaltMatcher { graphemes, index }
+    }
+
+    /// Turns Python's autojunk heuristic on or off; it is off unless
+    /// this is called, though `SequenceMatcher` has it on by default.
+    /// If the fixed string has at least 200
+    /// graphemes, any grapheme making up more than 1% of it is
+    /// treated as popular: it can't anchor a match, though it can
+    /// still extend one. This makes long comparisons faster, and
+    /// their scores the same as Python's, at the cost of missing
+    /// matches made mostly of common graphemes.
+    pub fn autojunk(mut self, autojunk: bool) -> Self {
+        self.index.set_autojunk(autojunk);
+        self
+    }
+
+    /// The gestalt ratio between `other` and the fixed string, the
+    /// same as `gestalt_ratio(other, fixed)`.
+    pub fn ratio(&self, other: &str) -> f64 {
+        let other_graphemes = graphemes(other);
+        let raw = raw_blocks_indexed(&other_graphemes, &self.index);
+        calculate_ratio(
+            matching_items(&raw),
+            other_graphemes.len() + self.graphemes.len(),
+        )
+    }
+
+    /// An upper bound on [`ratio`](Self::ratio), the same as
+    /// `quick_ratio(other, fixed)`. The grapheme counts of the fixed
+    /// string come from its index, so only `other` is counted here.
+    pub fn quick_ratio(&self, other: &str) -> f64 {
+        let other_graphemes = graphemes(other);
+        calculate_ratio(
+            self.index.common_items(&other_graphemes),
+            other_graphemes.len() + self.graphemes.len(),
+        )
+    }
+
+    /// An upper bound on [`ratio`](Self::ratio) and
+    /// [`quick_ratio`](Self::quick_ratio), the same as
+    /// `real_quick_ratio(other, fixed)`.
+    pub fn real_quick_ratio(&self, other: &str) -> f64 {
+        let len = UnicodeSegmentation::graphemes(other, true).count();
+        calculate_ratio(len.min(self.graphemes.len()), len + self.graphemes.len())
+    }
+
+    /// The matching blocks between `other` and the fixed string, the
+    /// same as `matching_blocks(other, fixed)`.
+    pub fn matching_blocks(&self, other: &str) -> Vec<Match> {
+        let other_graphemes = graphemes(other);
+        let raw = raw_blocks_indexed(&other_graphemes, &self.index);
+        finish_blocks(raw, other_graphemes.len(), self.graphemes.len())
+    }
+}
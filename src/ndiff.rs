@@ -0,0 +1,251 @@
+//! Human-readable line diffs in the format of Python's
+//! `difflib.ndiff`, with `?` lines pointing out the changes within
+//! similar lines.
+
+use crate::{DifflibCompat, Opcode, Tag};
+
+/// Lines only count as similar enough to be paired up, and marked
+/// character by character, if their ratio is at least this.
+const CUTOFF: f64 = 0.75;
+
+/// The characters ignored when pairing lines up, as in Python's
+/// `IS_CHARACTER_JUNK`.
+fn is_character_junk(c: &char) -> bool {
+    *c == ' ' || *c == '\t'
+}
+
+/// Python's `str.isspace`, which also counts the ASCII file, group,
+/// record and unit separators as whitespace.
+fn is_python_space(c: char) -> bool {
+    c.is_whitespace() || ('\x1c'..='\x1f').contains(&c)
+}
+
+/// Pending work for [`ndiff`].
+enum Step {
+    /// Output the differences between `a[alo..ahi]` and
+    /// `b[blo..bhi]`, pairing up similar lines if both are non-empty.
+    Replace(usize, usize, usize, usize),
+    /// Output `a[i]` and `b[j]`, which were paired up, as either a
+    /// common line or a marked change.
+    Pair(usize, usize),
+}
+
+/// One line of a delta, as indices into the two sequences of lines,
+/// before it is formatted.
+pub(crate) enum DeltaLine {
+    /// `a[i]` is also in `b`.
+    Common(usize),
+    /// `a[i]` was deleted.
+    Deleted(usize),
+    /// `b[j]` was inserted.
+    Inserted(usize),
+    /// `a[i]` was replaced by the similar line `b[j]`. The opcodes
+    /// are between their characters.
+    Changed(usize, usize, Vec<Opcode>),
+}
+
+struct Differ<'s> {
+    a: &'s [&'s str],
+    b: &'s [&'s str],
+    a_chars: Vec<Vec<char>>,
+    b_chars: Vec<Vec<char>>,
+    out: Vec<DeltaLine>,
+}
+
+impl Differ<'_> {
+    fn deleted(&mut self, alo: usize, ahi: usize) {
+        self.out.extend((alo..ahi).map(DeltaLine::Deleted));
+    }
+
+    fn inserted(&mut self, blo: usize, bhi: usize) {
+        self.out.extend((blo..bhi).map(DeltaLine::Inserted));
+    }
+
+    /// Finds the most similar pair of distinct lines, or failing that
+    /// the first pair of identical lines, in `a[alo..ahi]` and
+    /// `b[blo..bhi]`.
+    fn best_pair(&self, alo: usize, ahi: usize, blo: usize, bhi: usize) -> Option<(usize, usize)> {
+        // Python starts just under the cutoff, so that a ratio
+        // between the two is remembered but then rejected.
+        let mut best_ratio = 0.74;
+        let mut best = None;
+        let mut identical = None;
+        for j in blo..bhi {
+            let matcher = DifflibCompat::with_junk(&self.b_chars[j], is_character_junk);
+            for i in alo..ahi {
+                if self.a[i] == self.b[j] {
+                    identical = identical.or(Some((i, j)));
+                    continue;
+                }
+                let a = &self.a_chars[i];
+                if matcher.real_quick_ratio(a) > best_ratio && matcher.quick_ratio(a) > best_ratio {
+                    let ratio = matcher.ratio(a);
+                    if ratio > best_ratio {
+                        best_ratio = ratio;
+                        best = Some((i, j));
+                    }
+                }
+            }
+        }
+        if best_ratio < CUTOFF {
+            identical
+        } else {
+            best
+        }
+    }
+
+    /// Outputs the differences for a block of `a` that was replaced
+    /// by a block of `b`, like Python's `Differ._fancy_replace`. The
+    /// most similar pair of lines is used to split the blocks, and
+    /// the pieces on either side are handled the same way.
+    fn replace(&mut self, alo: usize, ahi: usize, blo: usize, bhi: usize) {
+        let mut steps = vec![Step::Replace(alo, ahi, blo, bhi)];
+        while let Some(step) = steps.pop() {
+            match step {
+                Step::Replace(alo, ahi, blo, bhi) if alo == ahi => self.inserted(blo, bhi),
+                Step::Replace(alo, ahi, blo, bhi) if blo == bhi => self.deleted(alo, ahi),
+                Step::Replace(alo, ahi, blo, bhi) => match self.best_pair(alo, ahi, blo, bhi) {
+                    Some((i, j)) => {
+                        steps.push(Step::Replace(i + 1, ahi, j + 1, bhi));
+                        steps.push(Step::Pair(i, j));
+                        steps.push(Step::Replace(alo, i, blo, j));
+                    }
+                    // Nothing is similar: output the shorter block
+                    // first.
+                    None if bhi - blo < ahi - alo => {
+                        self.inserted(blo, bhi);
+                        self.deleted(alo, ahi);
+                    }
+                    None => {
+                        self.deleted(alo, ahi);
+                        self.inserted(blo, bhi);
+                    }
+                },
+                Step::Pair(i, j) if self.a[i] == self.b[j] => self.out.push(DeltaLine::Common(i)),
+                Step::Pair(i, j) => {
+                    let (a, b) = (&self.a_chars[i], &self.b_chars[j]);
+                    let opcodes = DifflibCompat::with_junk(b, is_character_junk).opcodes(a);
+                    self.out.push(DeltaLine::Changed(i, j, opcodes));
+                }
+            }
+        }
+    }
+}
+
+/// Compares two lists of lines the way [`ndiff`] does, returning the
+/// delta unformatted.
+pub(crate) fn delta_lines(a: &[&str], b: &[&str]) -> Vec<DeltaLine> {
+    let mut differ = Differ {
+        a_chars: a.iter().map(|line| line.chars().collect()).collect(),
+        b_chars: b.iter().map(|line| line.chars().collect()).collect(),
+        a,
+        b,
+        out: Vec::new(),
+    };
+    for op in DifflibCompat::new(b).opcodes(a) {
+        match op.tag {
+            Tag::Replace => differ.replace(op.a.start, op.a.end, op.b.start, op.b.end),
+            Tag::Delete => differ.deleted(op.a.start, op.a.end),
+            Tag::Insert => differ.inserted(op.b.start, op.b.end),
+            Tag::Equal => differ.out.extend(op.a.map(DeltaLine::Common)),
+        }
+    }
+    differ.out
+}
+
+/// The `?` line under one side of a changed pair, marking the
+/// characters that were replaced (`^`), deleted (`-`) or inserted
+/// (`+`). Whitespace is kept under the line, so that tabs line the
+/// marks up with the characters above them. Returns `None` if there
+/// is nothing to mark.
+fn guide_line(line: &str, opcodes: &[Opcode], first: bool) -> Option<String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tags = String::new();
+    for op in opcodes {
+        let (range, tag) = match (op.tag, first) {
+            (Tag::Replace, true) => (op.a.clone(), '^'),
+            (Tag::Replace, false) => (op.b.clone(), '^'),
+            (Tag::Delete, true) => (op.a.clone(), '-'),
+            (Tag::Insert, false) => (op.b.clone(), '+'),
+            (_, true) => (op.a.clone(), ' '),
+            (_, false) => (op.b.clone(), ' '),
+        };
+        tags.extend(chars[range].iter().map(|&c| {
+            if tag == ' ' && is_python_space(c) {
+                c
+            } else {
+                tag
+            }
+        }));
+    }
+    let tags = tags.trim_end();
+    if tags.is_empty() {
+        None
+    } else {
+        Some(format!("? {}\n", tags))
+    }
+}
+
+/// Compares two lists of lines and returns a human-readable delta,
+/// like Python's `difflib.ndiff` with its default arguments. Each
+/// line of the delta starts with a two-character code:
+///
+/// - `"- "` for a line only in `a`,
+/// - `"+ "` for a line only in `b`,
+/// - `"  "` for a line in both,
+/// - `"? "` for a guide line under a changed line, marking the
+///   characters that changed with `^`, `-` and `+`.
+///
+/// Changed lines are paired up with the most similar line on the
+/// other side when their ratio is at least 0.75, ignoring spaces and
+/// tabs. Lines should keep their line terminators, as from
+/// `str::split_inclusive('\n')`; the delta has the same lines, with
+/// codes in front, and `?` lines end with `"\n"`.
+///
+/// Either input can be recovered from the delta with [`restore`].
+pub fn ndiff<S: AsRef<str>>(a: &[S], b: &[S]) -> Vec<String> {
+    let a: Vec<&str> = a.iter().map(AsRef::as_ref).collect();
+    let b: Vec<&str> = b.iter().map(AsRef::as_ref).collect();
+    let mut out = Vec::new();
+    for line in delta_lines(&a, &b) {
+        match line {
+            DeltaLine::Common(i) => out.push(format!("  {}", a[i])),
+            DeltaLine::Deleted(i) => out.push(format!("- {}", a[i])),
+            DeltaLine::Inserted(j) => out.push(format!("+ {}", b[j])),
+            DeltaLine::Changed(i, j, opcodes) => {
+                out.push(format!("- {}", a[i]));
+                out.extend(guide_line(a[i], &opcodes, true));
+                out.push(format!("+ {}", b[j]));
+                out.extend(guide_line(b[j], &opcodes, false));
+            }
+        }
+    }
+    out
+}
+
+/// Which of the two inputs to [`restore`] from a delta.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    /// The first sequence, `a`, passed to [`ndiff`].
+    First,
+    /// The second sequence, `b`, passed to [`ndiff`].
+    Second,
+}
+
+/// Recovers one of the two sequences of lines that a delta from
+/// [`ndiff`] was made from, like Python's `difflib.restore`. The
+/// lines are borrowed from the delta, without their codes, and keep
+/// their line terminators.
+pub fn restore<S: AsRef<str>>(delta: &[S], which: Side) -> Vec<&str> {
+    let tag = match which {
+        Side::First => "- ",
+        Side::Second => "+ ",
+    };
+    delta
+        .iter()
+        .filter_map(|line| {
+            let line = line.as_ref();
+            line.strip_prefix("  ").or_else(|| line.strip_prefix(tag))
+        })
+        .collect()
+}
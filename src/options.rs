@@ -0,0 +1,84 @@
+//! Options for preprocessing strings before they are compared.
+
+use std::borrow::Cow;
+
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::Tokenizer;
+
+/// A Unicode normalization form to put strings in before comparing
+/// them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Normalization {
+    /// Compare the strings as they are.
+    #[default]
+    None,
+    /// Canonical composition, so that precomposed and decomposed
+    /// forms of the same character compare equal.
+    Nfc,
+    /// Compatibility composition, which additionally folds
+    /// compatibility variants such as ligatures, full-width forms and
+    /// superscripts into their plain equivalents.
+    Nfkc,
+}
+
+/// How [`gestalt_ratio_with_options`](crate::gestalt_ratio_with_options)
+/// prepares and splits strings before comparing them. The default
+/// options compare strings exactly like
+/// [`gestalt_ratio`](crate::gestalt_ratio) does.
+///
+/// Preprocessing happens in a fixed order, regardless of the order
+/// the fields are set in: diacritics are stripped first, then the
+/// string is case folded, and finally it is normalized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Options {
+    /// The normalization form to apply.
+    pub normalization: Normalization,
+    /// Whether to apply full Unicode case folding, so that for
+    /// example "Straße" and "STRASSE" compare equal.
+    pub case_fold: bool,
+    /// Whether to remove combining marks after decomposing the
+    /// string, so that for example "café" and "cafe" compare equal.
+    pub strip_diacritics: bool,
+    /// How to split the prepared strings into elements.
+    pub tokenizer: Tokenizer,
+    /// The shortest common substring, in elements, that counts as a
+    /// match. Once the longest common substring of a sub-range is
+    /// shorter than this, the recursion stops there, so that short
+    /// coincidental matches between unrelated strings don't add to
+    /// their score. Zero and one both count every match.
+    pub min_block_len: usize,
+}
+
+impl Options {
+    fn normalize<'s>(&self, s: Cow<'s, str>) -> Cow<'s, str> {
+        match self.normalization {
+            Normalization::None => s,
+            Normalization::Nfc => Cow::Owned(s.nfc().collect()),
+            Normalization::Nfkc => Cow::Owned(s.nfkc().collect()),
+        }
+    }
+
+    /// Applies the preprocessing steps to `s`, borrowing it if there
+    /// is nothing to do.
+    pub(crate) fn prepare<'s>(&self, s: &'s str) -> Cow<'s, str> {
+        let mut s = Cow::Borrowed(s);
+        if self.strip_diacritics {
+            let stripped: String = match self.normalization {
+                Normalization::Nfkc => s.nfkd().filter(|&c| !is_combining_mark(c)).collect(),
+                _ => s.nfd().filter(|&c| !is_combining_mark(c)).collect(),
+            };
+            // Recompose what's left, like Hangul syllables, so the
+            // graphemes are the same as for text that had no marks.
+            s = Cow::Owned(stripped.nfc().collect());
+        }
+        if self.case_fold {
+            // Folding can produce characters that need normalizing,
+            // and normalizing can produce characters that need
+            // folding, so normalize on both sides.
+            s = Cow::Owned(caseless::default_case_fold_str(&self.normalize(s)));
+        }
+        self.normalize(s)
+    }
+}
@@ -0,0 +1,208 @@
+//! Line diffs for terminals, colored with ANSI escape codes.
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::ndiff::{delta_lines, DeltaLine};
+use crate::{graphemes, opcodes_seq, Tag};
+
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const REVERSE: &str = "\x1b[7m";
+const NO_REVERSE: &str = "\x1b[27m";
+const RESET: &str = "\x1b[0m";
+
+/// The pieces that changed lines are compared in, to pick out what
+/// changed within them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Highlight {
+    /// Words, and the spaces and punctuation between them, as split
+    /// by `split_word_bounds` from the unicode segmentation crate. A
+    /// word that changed is highlighted as a whole.
+    #[default]
+    Words,
+    /// Extended graphemes, as compared by
+    /// [`gestalt_ratio`](crate::gestalt_ratio).
+    Graphemes,
+}
+
+/// Renders the differences between two lists of lines for a terminal,
+/// like "expected" and "actual" output in a test failure.
+///
+/// Each line starts with the same two-character code as in
+/// [`ndiff`](crate::ndiff), and lines are paired up the same way.
+/// Lines only in the first list are red, and lines only in the second
+/// are green. When a line is replaced by a similar one, both are
+/// shown, with the pieces that differ in reverse video.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TermDiff {
+    /// The width of the terminal in columns. Longer lines are wrapped,
+    /// counting the width of each grapheme, so that wide characters
+    /// take two columns and combining marks none. Continuation lines
+    /// are indented past the code. The default is not to wrap.
+    pub width: Option<usize>,
+    /// What to compare changed lines in.
+    pub highlight: Highlight,
+    /// The number of columns between tab stops. Tabs are expanded to
+    /// spaces, so that wrapping knows how wide they are. The default
+    /// is 8.
+    pub tab_size: usize,
+}
+
+impl Default for TermDiff {
+    fn default() -> Self {
+        TermDiff {
+            width: None,
+            highlight: Highlight::default(),
+            tab_size: 8,
+        }
+    }
+}
+
+/// Writes one line of the diff, wrapping it when it reaches the width
+/// of the terminal.
+struct LineWriter<'o> {
+    out: &'o mut String,
+    color: &'static str,
+    /// The number of columns there is room for after the code.
+    room: Option<usize>,
+    column: usize,
+    /// Whether the text pushed next should be highlighted.
+    highlight: bool,
+    /// Whether the terminal is currently highlighting. This only
+    /// catches up with `highlight` when there is text to write, so
+    /// that no escape codes are left dangling at a line break.
+    highlighted: bool,
+}
+
+impl<'o> LineWriter<'o> {
+    fn start(out: &'o mut String, room: Option<usize>, color: &'static str, code: &str) -> Self {
+        out.push_str(color);
+        out.push_str(code);
+        LineWriter {
+            out,
+            color,
+            room,
+            column: 0,
+            highlight: false,
+            highlighted: false,
+        }
+    }
+
+    fn push(&mut self, text: &str) {
+        for grapheme in text.graphemes(true) {
+            let width = grapheme.width();
+            if let Some(room) = self.room {
+                // A grapheme too wide to ever fit still gets a line
+                // to itself, rather than an empty line before it.
+                if self.column > 0 && self.column + width > room {
+                    self.end_line();
+                    self.out.push_str(self.color);
+                    self.out.push_str("  ");
+                    self.column = 0;
+                }
+            }
+            if self.highlight != self.highlighted {
+                self.out
+                    .push_str(if self.highlight { REVERSE } else { NO_REVERSE });
+                self.highlighted = self.highlight;
+            }
+            self.out.push_str(grapheme);
+            self.column += width;
+        }
+    }
+
+    fn end_line(&mut self) {
+        if !self.color.is_empty() || self.highlighted {
+            self.out.push_str(RESET);
+        }
+        self.highlighted = false;
+        self.out.push('\n');
+    }
+}
+
+/// Drops the line terminator from `line`, and expands its tabs to the
+/// display column of the next tab stop.
+fn prepare(line: &str, tab_size: usize) -> String {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    let mut expanded = String::new();
+    let mut column = 0;
+    for grapheme in line.graphemes(true) {
+        if grapheme == "\t" {
+            if tab_size > 0 {
+                let spaces = tab_size - column % tab_size;
+                expanded.push_str(&" ".repeat(spaces));
+                column += spaces;
+            }
+        } else {
+            expanded.push_str(grapheme);
+            column += grapheme.width();
+        }
+    }
+    expanded
+}
+
+impl TermDiff {
+    fn pieces<'s>(&self, line: &'s str) -> Vec<&'s str> {
+        match self.highlight {
+            Highlight::Words => line.split_word_bounds().collect(),
+            Highlight::Graphemes => graphemes(line),
+        }
+    }
+
+    fn room(&self) -> Option<usize> {
+        self.width.map(|width| width.saturating_sub(2).max(1))
+    }
+
+    fn whole_line(&self, out: &mut String, color: &'static str, code: &str, line: &str) {
+        let mut writer = LineWriter::start(out, self.room(), color, code);
+        writer.push(line);
+        writer.end_line();
+    }
+
+    /// Writes a pair of similar lines, highlighting the pieces of each
+    /// that aren't in the other.
+    fn changed_pair(&self, out: &mut String, a: &str, b: &str) {
+        let (a, b) = (self.pieces(a), self.pieces(b));
+        let opcodes = opcodes_seq(&a, &b);
+        for (color, code, pieces, first) in [(RED, "- ", &a, true), (GREEN, "+ ", &b, false)] {
+            let mut writer = LineWriter::start(out, self.room(), color, code);
+            for op in &opcodes {
+                let range = if first { op.a.clone() } else { op.b.clone() };
+                writer.highlight = op.tag != Tag::Equal;
+                for piece in &pieces[range] {
+                    writer.push(piece);
+                }
+            }
+            writer.end_line();
+        }
+    }
+
+    /// Returns the differences between `a` and `b`, with every line
+    /// of both, as text to print to a terminal. Lines may keep their
+    /// line terminators; every line of the output ends with `"\n"`.
+    pub fn render<S: AsRef<str>>(&self, a: &[S], b: &[S]) -> String {
+        let a: Vec<String> = a
+            .iter()
+            .map(|line| prepare(line.as_ref(), self.tab_size))
+            .collect();
+        let b: Vec<String> = b
+            .iter()
+            .map(|line| prepare(line.as_ref(), self.tab_size))
+            .collect();
+        let a: Vec<&str> = a.iter().map(AsRef::as_ref).collect();
+        let b: Vec<&str> = b.iter().map(AsRef::as_ref).collect();
+
+        let mut out = String::new();
+        for line in delta_lines(&a, &b) {
+            match line {
+                DeltaLine::Common(i) => self.whole_line(&mut out, "", "  ", a[i]),
+                DeltaLine::Deleted(i) => self.whole_line(&mut out, RED, "- ", a[i]),
+                DeltaLine::Inserted(j) => self.whole_line(&mut out, GREEN, "+ ", b[j]),
+                DeltaLine::Changed(i, j, _) => self.changed_pair(&mut out, a[i], b[j]),
+            }
+        }
+        out
+    }
+}
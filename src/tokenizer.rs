@@ -0,0 +1,53 @@
+//! The ways a string can be split into the elements that are
+//! compared.
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// How to split strings into the elements that the gestalt ratio
+/// compares, for [`gestalt_ratio_with`](crate::gestalt_ratio_with).
+/// The tokenizers that split into words or lines drop the separators
+/// between them, so those don't count towards the score.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Tokenizer {
+    /// Extended grapheme clusters, as used by
+    /// [`gestalt_ratio`](crate::gestalt_ratio).
+    #[default]
+    Graphemes,
+    /// Legacy grapheme clusters, as defined by the unicode
+    /// segmentation crate.
+    LegacyGraphemes,
+    /// Unicode scalar values, as returned by `str::chars`. This is
+    /// how Python compares strings.
+    Chars,
+    /// Individual bytes of the UTF-8 encoding.
+    Bytes,
+    /// Words, as returned by `unicode_words` from the unicode
+    /// segmentation crate. Punctuation is dropped along with
+    /// whitespace.
+    UnicodeWords,
+    /// Runs of non-whitespace, as returned by `str::split_whitespace`.
+    Whitespace,
+    /// Lines, as returned by `str::lines`.
+    Lines,
+}
+
+impl Tokenizer {
+    /// Splits `s` into tokens. Tokens are returned as bytes so that
+    /// every tokenizer, including [`Tokenizer::Bytes`], produces the
+    /// same type; two tokens are equal exactly when their strings
+    /// are.
+    pub(crate) fn tokenize(self, s: &str) -> Vec<&[u8]> {
+        match self {
+            Tokenizer::Graphemes => s.graphemes(true).map(str::as_bytes).collect(),
+            Tokenizer::LegacyGraphemes => s.graphemes(false).map(str::as_bytes).collect(),
+            Tokenizer::Chars => s
+                .char_indices()
+                .map(|(i, c)| &s.as_bytes()[i..i + c.len_utf8()])
+                .collect(),
+            Tokenizer::Bytes => s.as_bytes().chunks(1).collect(),
+            Tokenizer::UnicodeWords => s.unicode_words().map(str::as_bytes).collect(),
+            Tokenizer::Whitespace => s.split_whitespace().map(str::as_bytes).collect(),
+            Tokenizer::Lines => s.lines().map(str::as_bytes).collect(),
+        }
+    }
+}
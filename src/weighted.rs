@@ -0,0 +1,107 @@
+//! Gestalt ratio with a weight for each element.
+
+use crate::{collect_matching_blocks, Match};
+
+/// Like the longest common run search in the crate root, but finds
+/// the run with the most total weight instead of the most elements.
+/// `weight(i, j)` is the weight of matching `outer[i]` with
+/// `inner[j]`. Returns the weight of the run, its length, and its
+/// ending index in `outer` and `inner`.
+fn heaviest_common_run<O, I, F, W, K>(
+    outer: &[O],
+    inner: &[I],
+    eq: &F,
+    weight: W,
+    order: impl Fn(usize, usize) -> K,
+) -> (f64, usize, usize, usize)
+where
+    F: Fn(&O, &I) -> bool,
+    W: Fn(usize, usize) -> f64,
+    K: Ord,
+{
+    let mut max_weight = 0.0;
+    let mut max_length = 0;
+    let mut ending_outer = outer.len();
+    let mut ending_inner = inner.len();
+    let mut prev = vec![(0.0, 0); inner.len() + 1];
+    let mut cur = vec![(0.0, 0); inner.len() + 1];
+
+    for (i, c1) in outer.iter().enumerate() {
+        for (j, c2) in inner.iter().enumerate() {
+            cur[j + 1] = if eq(c1, c2) {
+                (prev[j].0 + weight(i, j), prev[j].1 + 1)
+            } else {
+                (0.0, 0)
+            };
+            let (run_weight, length) = cur[j + 1];
+            if run_weight > max_weight
+                || (run_weight == max_weight
+                    && length > 0
+                    && max_length > 0
+                    && order(i + 1, j + 1) < order(ending_outer, ending_inner))
+            {
+                max_weight = run_weight;
+                max_length = length;
+                ending_outer = i + 1;
+                ending_inner = j + 1;
+            }
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    (max_weight, max_length, ending_outer, ending_inner)
+}
+
+/// A weighted version of [`gestalt_ratio_seq`](crate::gestalt_ratio_seq),
+/// for when some elements matter more than others; for example
+/// matching identifiers in code should count for more than matching
+/// semicolons.
+///
+/// Each step of the recursion picks the common substring with the
+/// most total weight, rather than the longest one, and the ratio is
+/// twice the weight of the matched elements over the total weight of
+/// both sequences. The weight of a matched pair is the weight of the
+/// element from `s1`, so elements that are equal should have equal
+/// weights. With every weight 1.0, this is the same as
+/// `gestalt_ratio_seq`.
+///
+/// Weights must not be negative. Elements of zero weight are free to
+/// match or not, and don't count towards the total; if the total
+/// weight of both sequences is zero, the ratio is 1.0.
+pub fn gestalt_ratio_weighted<T, W>(s1: &[T], s2: &[T], weight: W) -> f64
+where
+    T: Eq,
+    W: Fn(&T) -> f64,
+{
+    let weights1: Vec<f64> = s1.iter().map(&weight).collect();
+    let total: f64 = weights1.iter().sum::<f64>() + s2.iter().map(&weight).sum::<f64>();
+    debug_assert!(weights1.iter().all(|&w| w >= 0.0), "negative weight");
+
+    let mut blocks = Vec::new();
+    let mut heaviest = |alo: usize, ahi: usize, blo: usize, bhi: usize| {
+        let (sub1, sub2) = (&s1[alo..ahi], &s2[blo..bhi]);
+        let (run_weight, size, end1, end2) = if sub2.len() <= sub1.len() {
+            heaviest_common_run(sub1, sub2, &T::eq, |i, _| weights1[alo + i], |i, j| (i, j))
+        } else {
+            let (run_weight, size, end2, end1) =
+                heaviest_common_run(sub2, sub1, &T::eq, |_, i| weights1[alo + i], |j, i| (i, j));
+            (run_weight, size, end1, end2)
+        };
+        Match {
+            a: alo + end1 - size,
+            b: blo + end2 - size,
+            size: if run_weight > 0.0 { size } else { 0 },
+        }
+    };
+    collect_matching_blocks(0, s1.len(), 0, s2.len(), &mut heaviest, &mut blocks);
+
+    // Summing nothing with `sum` gives -0.0, so start from 0.0.
+    let matched = blocks
+        .iter()
+        .flat_map(|m| &weights1[m.a..m.a + m.size])
+        .fold(0.0, |acc, w| acc + w);
+    if total == 0.0 {
+        1.0
+    } else {
+        2.0 * matched / total
+    }
+}
@@ -0,0 +1,77 @@
+#!/usr/bin/env python3
+"""Generates difflib_corpus.tsv, the expected scores for the
+differential tests in tests/difflib_corpus.rs.
+
+Each line holds two strings and the scores Python's SequenceMatcher
+gives them, separated by tabs:
+
+    a    b    ratio()    ratio() with autojunk=False
+
+Strings are written as space-separated hexadecimal code points, so
+that no escaping is needed, and scores as Python's repr() of the
+float, which Rust parses back to the same bits.
+
+Run from this directory with: python3 generate_difflib_corpus.py
+"""
+
+import random
+from difflib import SequenceMatcher
+
+ALPHABETS = [
+    "ab",
+    "abc",
+    "abcd ",
+    "abcdefghijklmnopqrstuvwxyz",
+    "the quick brown fox",
+    "x²+y³ éü",
+]
+
+
+def encode(s):
+    return " ".join("%x" % ord(c) for c in s)
+
+
+def mutate(rng, s, alphabet):
+    chars = list(s)
+    for _ in range(rng.randrange(0, max(1, len(chars) // 4) + 1)):
+        op = rng.randrange(3)
+        pos = rng.randrange(len(chars) + 1)
+        if op == 0 or not chars:
+            chars.insert(pos, rng.choice(alphabet))
+        elif op == 1:
+            del chars[min(pos, len(chars) - 1)]
+        else:
+            chars[min(pos, len(chars) - 1)] = rng.choice(alphabet)
+    return "".join(chars)
+
+
+def pairs(rng):
+    yield "", ""
+    yield "", "abc"
+    yield "abc", ""
+    yield "Wikimedia", "Wikimania"
+    yield "Ebojfm Mzpm", "Ebfo ef Mfpo"
+    yield "Ebfo ef Mfpo", "Ebojfm Mzpm"
+    yield "x² + y²", "y² + z²"
+    for alphabet in ALPHABETS:
+        for max_len in (8, 40, 400):
+            for _ in range(15):
+                a = "".join(rng.choice(alphabet) for _ in range(rng.randrange(max_len + 1)))
+                if rng.random() < 0.5:
+                    b = mutate(rng, a, alphabet)
+                else:
+                    b = "".join(rng.choice(alphabet) for _ in range(rng.randrange(max_len + 1)))
+                yield a, b
+
+
+def main():
+    rng = random.Random(1988)
+    with open("difflib_corpus.tsv", "w", encoding="ascii") as out:
+        for a, b in pairs(rng):
+            ratio = SequenceMatcher(None, a, b).ratio()
+            plain = SequenceMatcher(None, a, b, autojunk=False).ratio()
+            out.write("%s\t%s\t%r\t%r\n" % (encode(a), encode(b), ratio, plain))
+
+
+if __name__ == "__main__":
+    main()
@@ -0,0 +1,86 @@
+//! Differential tests against Python's difflib, using the scores in
+//! data/difflib_corpus.tsv. See data/generate_difflib_corpus.py for
+//! how they were produced.
+
+use gestalt_ratio::{difflib_ratio, gestalt_ratio_seq, DifflibCompat};
+
+struct Case {
+    a: String,
+    b: String,
+    ratio: f64,
+    ratio_without_autojunk: f64,
+}
+
+fn decode(field: &str) -> String {
+    field
+        .split_whitespace()
+        .map(|hex| {
+            let code = u32::from_str_radix(hex, 16).expect("bad code point");
+            char::from_u32(code).expect("bad code point")
+        })
+        .collect()
+}
+
+fn corpus() -> Vec<Case> {
+    include_str!("data/difflib_corpus.tsv")
+        .lines()
+        .map(|line| {
+            let fields: Vec<&str> = line.split('\t').collect();
+            assert_eq!(fields.len(), 4, "{:?}", line);
+            Case {
+                a: decode(fields[0]),
+                b: decode(fields[1]),
+                ratio: fields[2].parse().unwrap(),
+                ratio_without_autojunk: fields[3].parse().unwrap(),
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn difflib_ratio_matches_python() {
+    for case in corpus() {
+        let ratio = difflib_ratio(&case.a, &case.b);
+        assert_eq!(
+            ratio.to_bits(),
+            case.ratio.to_bits(),
+            "{:?} {:?}",
+            case.a,
+            case.b
+        );
+    }
+}
+
+#[test]
+fn difflib_compat_without_autojunk_matches_python() {
+    for case in corpus() {
+        let a: Vec<char> = case.a.chars().collect();
+        let b: Vec<char> = case.b.chars().collect();
+        let ratio = DifflibCompat::new(&b).autojunk(false).ratio(&a);
+        assert_eq!(
+            ratio.to_bits(),
+            case.ratio_without_autojunk.to_bits(),
+            "{:?} {:?}",
+            case.a,
+            case.b
+        );
+    }
+}
+
+#[test]
+/// Without autojunk, the plain gestalt ratio over code points already
+/// breaks ties the way Python does.
+fn gestalt_ratio_seq_matches_python_without_autojunk() {
+    for case in corpus() {
+        let a: Vec<char> = case.a.chars().collect();
+        let b: Vec<char> = case.b.chars().collect();
+        let ratio = gestalt_ratio_seq(&a, &b);
+        assert_eq!(
+            ratio.to_bits(),
+            case.ratio_without_autojunk.to_bits(),
+            "{:?} {:?}",
+            case.a,
+            case.b
+        );
+    }
+}